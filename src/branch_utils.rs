@@ -2,7 +2,7 @@ use indicatif::ProgressBar;
 use inquire::Confirm;
 use log::{debug, error, info};
 use serde_json;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 use std::{
     io::{self, Write},
@@ -13,7 +13,13 @@ use std::{
 use lazy_static::lazy_static;
 use regex::Regex;
 
-use crate::storage;
+use crate::{
+    codeowners, pr_body,
+    pr_metadata::{self, PrMetadata},
+    pr_title, prompts, stack, storage, text_merge,
+    types::github_types::GithubPullRequest,
+    utils::git::{git_output, run_git},
+};
 
 #[derive(Debug)]
 pub struct GithubRepoParts {
@@ -32,8 +38,11 @@ lazy_static! {
     static ref CI_REGEX: Regex = Regex::new(r"^\.|Dockerfile|/iac/").unwrap();
 }
 
-pub fn validate_branch(git_branch: &str) {
-    let protected_branches = vec!["main", "production"];
+pub const DEFAULT_BASE_BRANCH: &str = "main";
+
+pub fn validate_branch(directory: &str, git_branch: &str) {
+    let default_branch = default_branch(directory);
+    let protected_branches = ["main", "production", default_branch.as_str()];
     let stdout = io::stdout(); // get the global stdout entity
     let mut handle = io::BufWriter::new(&stdout); // optional: wrap that handle in a buffer
     if protected_branches.contains(&git_branch) {
@@ -43,6 +52,71 @@ pub fn validate_branch(git_branch: &str) {
     }
 }
 
+// `base_branch` from the repository config, otherwise the branch origin/HEAD points to
+pub fn default_branch(directory: &str) -> String {
+    if let Some(base) = storage::load_repo_config(directory).base_branch {
+        return base;
+    }
+    match git_output(directory, "symbolic-ref --short refs/remotes/origin/HEAD") {
+        Some(origin_head) => match origin_head.strip_prefix("origin/") {
+            Some(branch) if !branch.is_empty() => branch.to_owned(),
+            _ => DEFAULT_BASE_BRANCH.to_owned(),
+        },
+        None => DEFAULT_BASE_BRANCH.to_owned(),
+    }
+}
+
+fn pr_base_branch(directory: &str, git_branch: &str) -> Option<String> {
+    let cmd_arg = format!(
+        "cd {} && gh pr view '{}' --json baseRefName --jq .baseRefName",
+        directory, git_branch
+    );
+    let output = Command::new("sh").arg("-c").arg(cmd_arg).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_owned()).filter(|b| !b.is_empty())
+}
+
+// The branch `git_branch` is merged into: its parent in a stack, the base of its PR or the
+// default branch
+pub fn base_branch(directory: &str, git_branch: &str) -> String {
+    let base = stack::parent_branch(directory, git_branch)
+        .or_else(|| pr_base_branch(directory, git_branch))
+        .unwrap_or_else(|| default_branch(directory));
+    debug!("Base branch of {} is {}", git_branch, base);
+    base
+}
+
+// The remote version of the base, unless the local one has commits that weren't pushed
+pub fn base_ref(directory: &str, base: &str) -> String {
+    let remote = format!("origin/{}", base);
+    match run_git(directory, &format!("rev-parse --verify --quiet {}", remote))
+        && run_git(
+            directory,
+            &format!("merge-base --is-ancestor {} {}", base, remote),
+        ) {
+        true => remote,
+        false => base.to_owned(),
+    }
+}
+
+// Commit the branch forked from, so commits merged into the base since then aren't part of
+// the branch diff
+pub fn diff_base(directory: &str, base: &str) -> String {
+    git_output(
+        directory,
+        &format!("merge-base {} HEAD", base_ref(directory, base)),
+    )
+    .unwrap_or_else(|| base.to_owned())
+}
+
+pub fn branch_commit_count(directory: &str, diff_base: &str) -> Option<usize> {
+    git_output(directory, &format!("rev-list --count {}..HEAD", diff_base))?
+        .parse()
+        .ok()
+}
+
 pub fn issue_id(git_branch: &str) -> String {
     debug!("Git branch is {}", git_branch);
     let Some(caps) = ISSUE_REGEX.captures(git_branch) else {
@@ -108,48 +182,198 @@ pub fn changed_file_names(directory: &str) -> Vec<String> {
     return files.iter().map(|s| s.trim().to_owned()).collect();
 }
 
-fn suggest_type(used_types: &Vec<&str>, is_new_branch: &bool) -> Option<String> {
-    info!("Trying to suggest a PR type");
-    if used_types.len() == 1 {
-        let single_type = used_types.first().unwrap();
-        info!("Found only one type: {}", single_type);
-        if single_type == &"feat" && is_new_branch != &true {
-            return Some("refactor".to_string());
+// Every file changed in the branch, not only the staged ones
+pub fn branch_changed_file_names(directory: &str, base: &str) -> Vec<String> {
+    let cmd_arg = format!("cd {directory} && git diff --name-only {base}...HEAD");
+    let output = match Command::new("sh").arg("-c").arg(cmd_arg).output() {
+        Ok(x) if x.status.success() => x,
+        _ => {
+            debug!("Couldn't find the files changed since {}", base);
+            return vec![];
         }
-        return Some(single_type.to_string());
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| l.trim().to_owned())
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct FileChange {
+    pub path: String,
+    pub category: &'static str,
+    pub added: usize,
+    pub removed: usize,
+    pub whitespace_only: bool,
+}
+
+impl FileChange {
+    // Every file weights at least one line so binary and empty files are still counted
+    pub fn weight(&self) -> usize {
+        (self.added + self.removed).max(1)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeSuggestion {
+    pub commit_type: Option<String>,
+    pub confidence: f32,
+    pub used_types: usize,
+    pub category_weights: Vec<(&'static str, usize)>,
+}
+
+pub fn classify_file(file: &str) -> &'static str {
+    if CI_REGEX.is_match(file) {
+        return "ci";
     }
-    if used_types.len() > 1 && is_new_branch == &true {
-        info!("New branch and more than one type: suggesting feature");
-        return Some("feat".to_string());
+    if TEST_REGEX.is_match(file) {
+        return "test";
     }
-    if used_types.len() > 1 && is_new_branch != &true && used_types.first().unwrap() == &"test" {
-        info!("Existing branch and more than one type but more used in test: suggesting test");
-        return Some("test".to_string());
+    if DOCS_REGEX.is_match(file) {
+        return "docs";
     }
-    if used_types.len() > 1 && is_new_branch != &true {
-        info!("Existing branch and more than one type: suggesting refactor");
-        return Some("refactor".to_string());
+    if BUILD_REGEX.is_match(file) {
+        return "build";
     }
+    "code"
+}
+
+fn numstat(directory: &str, extra_args: &str) -> HashMap<String, (usize, usize)> {
+    let cmd_arg = format!("cd {directory} && git diff --cached --numstat {extra_args}");
+    let output = Command::new("sh").arg("-c").arg(cmd_arg).output().unwrap();
+    let mut stats = HashMap::new();
+    if !output.status.success() {
+        error!("{:?}", str::from_utf8(&output.stderr).unwrap_or_default());
+        return stats;
+    }
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let parts: Vec<&str> = line.split('\t').collect();
+        if parts.len() < 3 {
+            continue;
+        }
+        // Binary files are reported as `-`
+        let added = parts[0].parse::<usize>().unwrap_or(0);
+        let removed = parts[1].parse::<usize>().unwrap_or(0);
+        stats.insert(parts[parts.len() - 1].to_owned(), (added, removed));
+    }
+    stats
+}
+
+pub fn staged_file_changes(directory: &str) -> Vec<FileChange> {
+    let line_stats = numstat(directory, "");
+    let non_whitespace_stats = numstat(directory, "-w --ignore-blank-lines");
+    changed_file_names(directory)
+        .into_iter()
+        .filter(|file| !file.is_empty())
+        .map(|file| {
+            let (added, removed) = line_stats.get(&file).cloned().unwrap_or((0, 0));
+            let whitespace_only = added + removed > 0
+                && non_whitespace_stats
+                    .get(&file)
+                    .is_none_or(|(a, r)| a + r == 0);
+            FileChange {
+                category: classify_file(&file),
+                path: file,
+                added,
+                removed,
+                whitespace_only,
+            }
+        })
+        .collect()
+}
+
+fn type_from_issue_labels(issue_labels: &[String]) -> Option<&'static str> {
+    for label in issue_labels {
+        let label = label.to_lowercase();
+        if label.contains("bug") || label.contains("fix") || label.contains("defect") {
+            return Some("fix");
+        }
+        if label.contains("perf") {
+            return Some("perf");
+        }
+        if label.contains("refactor") || label.contains("tech debt") {
+            return Some("refactor");
+        }
+        if label.contains("doc") {
+            return Some("docs");
+        }
+        if label.contains("feature") || label.contains("enhancement") {
+            return Some("feat");
+        }
+    }
+    None
+}
+
+pub fn suggest_type(
+    changes: &[FileChange],
+    is_new_branch: bool,
+    issue_labels: &[String],
+) -> TypeSuggestion {
+    info!("Trying to suggest a PR type");
+    let mut weights: HashMap<&'static str, usize> = HashMap::new();
+    for change in changes {
+        *weights.entry(change.category).or_insert(0) += change.weight();
+    }
+    let mut category_weights: Vec<(&'static str, usize)> = weights.into_iter().collect();
+    category_weights.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    info!("Changed types by weight: {:?}", category_weights);
+
+    let mut suggestion = TypeSuggestion {
+        commit_type: None,
+        confidence: 0.0,
+        used_types: category_weights.len(),
+        category_weights: category_weights.clone(),
+    };
+    if changes.is_empty() {
+        return suggestion;
+    }
+
+    let total_weight: usize = category_weights.iter().map(|(_, w)| w).sum();
+    let has_code = category_weights.iter().any(|(c, _)| *c == "code");
+    let has_test = category_weights.iter().any(|(c, _)| *c == "test");
+    let total_added: usize = changes.iter().map(|c| c.added).sum();
+    let (dominant, dominant_weight) = category_weights[0];
+    let dominant_share = dominant_weight as f32 / total_weight as f32;
+
+    let (commit_type, confidence) = if changes.iter().all(|c| c.whitespace_only) {
+        info!("Only whitespace changes: suggesting style");
+        ("style", 0.9)
+    } else if let Some(label_type) = type_from_issue_labels(issue_labels) {
+        info!("Issue labels suggest {}", label_type);
+        (label_type, 0.8)
+    } else if total_added == 0 {
+        info!("Only deletions found");
+        match has_code {
+            true => ("refactor", 0.7),
+            false => ("chore", 0.7),
+        }
+    } else if has_code && has_test {
+        info!("Source and tests touched");
+        match is_new_branch {
+            true => ("feat", 0.7),
+            false => ("fix", 0.6),
+        }
+    } else if dominant == "code" {
+        match is_new_branch {
+            true => ("feat", 0.6 * dominant_share),
+            false => ("refactor", 0.5 * dominant_share),
+        }
+    } else {
+        (dominant, 0.9 * dominant_share)
+    };
 
-    return None; //"".to_string();
+    suggestion.commit_type = Some(commit_type.to_owned());
+    suggestion.confidence = confidence;
+    info!("Proposed type: {:?}", suggestion);
+    suggestion
 }
 
-pub fn find_changed_file_types(directory: &str, is_new_branch: &bool) -> (Option<String>, usize) {
-    let mut changed_types = HashMap::from([
-        ("test", 0),
-        ("docs", 0),
-        ("build", 0),
-        ("ci", 0),
-        ("code", 0),
-        // ?
-        ("feat", 0),
-        ("fix", 0),
-        ("style", 0),
-        ("refactor", 0),
-        ("perf", 0),
-        ("chore", 0),
-        ("revert", 0),
-    ]);
+pub fn find_changed_file_types(
+    directory: &str,
+    is_new_branch: &bool,
+    issue_labels: &[String],
+) -> TypeSuggestion {
     let files_changed = changed_file_names(directory);
     if files_changed.is_empty() || files_changed.len() == 1 && files_changed[0].len() == 0 {
         let no_staged_files_prompt = Confirm::new(
@@ -168,49 +392,8 @@ pub fn find_changed_file_types(directory: &str, is_new_branch: &bool) -> (Option
         }
     }
     info!("Files changed: {:?}", files_changed);
-    for file in &files_changed {
-        if file.is_empty() {
-            continue;
-        }
-        if CI_REGEX.is_match(file) {
-            info!("Matches CI");
-            changed_types.entry("ci").and_modify(|count| *count += 1);
-            continue;
-        }
-        if TEST_REGEX.is_match(file) {
-            info!("Matches test");
-            changed_types.entry("test").and_modify(|count| *count += 1);
-            continue;
-        }
-        if DOCS_REGEX.is_match(file) {
-            info!("Matches docs");
-            changed_types.entry("docs").and_modify(|count| *count += 1);
-            continue;
-        }
-        if BUILD_REGEX.is_match(file) {
-            info!("Matches build");
-            changed_types.entry("build").and_modify(|count| *count += 1);
-            continue;
-        }
-        info!("No matches");
-        changed_types.entry("feat").and_modify(|count| *count += 1);
-    }
-
-    changed_types.retain(|_, v| *v != 0);
-    info!("Changed types: {:?}", changed_types);
-    let mut sorted_changed_types: Vec<_> = changed_types.into_iter().collect();
-    sorted_changed_types.sort_by(|a, b| b.0.cmp(&a.0));
-    info!("Changed types: {:?}", sorted_changed_types);
-    let used_types: Vec<&str> = sorted_changed_types.into_iter().map(|(k, _)| k).collect();
-    // let used_types: Vec<&str> = sorted_changed_types.keys().cloned().collect();
-    let proposed_type = suggest_type(&used_types, &is_new_branch);
-    info!("Proposed type: {:?}", proposed_type);
-    if proposed_type.is_none() {
-        return (None, used_types.len());
-    }
-
-    let proposed_string = Option::expect(proposed_type, "Expected a proposed reason");
-    return (Some(proposed_string), used_types.len());
+    let changes = staged_file_changes(directory);
+    suggest_type(&changes, *is_new_branch, issue_labels)
 }
 
 pub fn commit_pr(
@@ -454,17 +637,62 @@ pub async fn push_pr(
             return Some(0);
         }
         info!("No pr created\nCommit message: {:?}", commit_message);
-        let create_pr_prompt = Confirm::new(
-            "The branch was pushed but there is no PR created. Do you want to create it?",
-        )
-        .with_default(true)
-        .prompt();
+        let create_pr_prompt = match ci_mode {
+            true => Ok(true),
+            false => Confirm::new(
+                "The branch was pushed but there is no PR created. Do you want to create it?",
+            )
+            .with_default(true)
+            .prompt(),
+        };
 
         match create_pr_prompt {
             Ok(response) => {
                 if response {
-                    create_pr(directory, commit_message, pr_template)
+                    let diff_base = diff_base(directory, &base_branch(directory, git_branch));
+                    let title = pr_title::pr_title(
+                        directory,
+                        git_branch,
+                        commit_message.unwrap_or(""),
+                        &diff_base,
+                        ci_mode,
+                    );
+                    let mut metadata = pr_metadata::pr_metadata(directory, git_branch, &title);
+                    codeowners::apply_reviewer_suggestion(
+                        directory,
+                        &branch_changed_file_names(directory, &diff_base),
+                        pr_metadata::pull_request_config(directory)
+                            .codeowners_reviewers
+                            .as_deref()
+                            .unwrap_or(codeowners::SUGGEST_REVIEWERS),
+                        ci_mode,
+                        &mut metadata,
+                    );
+                    let pr_body = pr_template.map(|body| {
+                        stack::with_stack_section(
+                            &body,
+                            stack::stack_section(directory, git_branch).as_deref(),
+                        )
+                    });
+                    let status = create_pr(directory, Some(&title), pr_body.clone(), &metadata)
                         .expect("PR should be created");
+                    if status == Some(0) {
+                        let _ = storage::update_branch_config(git_branch, directory, |config| {
+                            config.pushed_pr_body = pr_body;
+                            config.pushed_pr_title = Some(title);
+                        });
+                        // The other PRs of the stack now link to this one
+                        if stack::parent_branch(directory, git_branch).is_some() {
+                            stack::sync_stack(directory, git_branch);
+                        }
+                    } else {
+                        writeln!(handle, "\x1b[1;31mThe PR could not be created.\x1b[0m")
+                            .unwrap_or_default();
+                        let _ = handle.flush();
+                        if ci_mode {
+                            process::exit(1);
+                        }
+                    }
                 }
             }
             Err(_) => {}
@@ -524,6 +752,7 @@ pub fn create_pr(
     directory: &str,
     commit_message: Option<&str>,
     pr_template: Option<String>,
+    metadata: &PrMetadata,
 ) -> Result<Option<i32>, io::Error> {
     let pr_body = match pr_template {
         Some(template) => template,
@@ -532,9 +761,13 @@ pub fn create_pr(
 
     let title = commit_message.unwrap_or("Default PR Title");
 
+    // Single quoted, the body can have backticks from the stack table and code samples
     let cmd_arg = format!(
-        r#"cd {} && gh pr create -a @me --body "{}" -t "{}""#,
-        directory, pr_body, title
+        "cd {} && gh pr create --body {} -t {}{}",
+        directory,
+        pr_metadata::shell_quote(&pr_body),
+        pr_metadata::shell_quote(title),
+        metadata.create_args()
     );
 
     info!("Executing command: {}", cmd_arg);
@@ -543,6 +776,12 @@ pub fn create_pr(
     io::stderr().write_all(&output.stderr).unwrap();
     io::stdout().write_all(&output.stdout).unwrap();
 
+    if output.status.success() {
+        if let Some(strategy) = &metadata.auto_merge {
+            pr_metadata::enable_auto_merge(directory, strategy);
+        }
+    }
+
     Ok(output.status.code())
 }
 pub fn check_existing_pr(directory: &str) -> bool {
@@ -581,13 +820,100 @@ pub fn check_existing_pr(directory: &str) -> bool {
     }
 }
 
-pub fn update_pull_request(directory: &str, pr_template: &str) -> Result<(), io::Error> {
+// Merges the local body with the edits made on Github since the last push
+fn merge_pr_body(
+    git_branch: &str,
+    directory: &str,
+    local: &str,
+    pr_data: &GithubPullRequest,
+    ci_mode: bool,
+) -> String {
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    let remote = pr_data.body.clone().unwrap_or_default();
+    let last_pushed = match storage::get_branch_config(git_branch, directory) {
+        Ok(Some(config)) => config.pushed_pr_body,
+        _ => None,
+    };
+    if last_pushed.is_none() && remote.trim().is_empty() {
+        return local.to_owned();
+    }
+
+    // Checkbox marks and the stack table are left out of the merge, they are set again
+    // afterwards
+    let unticked = HashSet::new();
+    let prepare = |body: &str| pr_body::set_checkboxes(&stack::strip_stack_section(body), &unticked);
+    // PRs opened before the pushed body was saved have no base, the remote one is used so the
+    // local body wins instead of conflicting as a whole
+    let base = last_pushed.unwrap_or_else(|| remote.clone());
+    let chunks = text_merge::merge(
+        &prepare(&base),
+        &prepare(local),
+        &prepare(&remote),
+    );
+    if text_merge::has_conflicts(&chunks) && ci_mode {
+        let marked = text_merge::render(&chunks);
+        let _ = storage::update_branch_config(git_branch, directory, |config| {
+            config.pr_template = Some(marked);
+        });
+        writeln!(
+            handle,
+            "\x1b[1;31mThe PR body was changed locally and on Github.\x1b[0m Resolve the conflict markers in .commit_message/{}.yaml and run the update again.",
+            git_branch
+        )
+        .unwrap_or_default();
+        let _ = handle.flush();
+        process::exit(1);
+    }
+
+    let mut merged: Vec<String> = vec![];
+    for chunk in chunks {
+        match chunk {
+            text_merge::MergeChunk::Resolved(lines) => merged.extend(lines),
+            text_merge::MergeChunk::Conflict { local, remote, .. } => {
+                merged.extend(prompts::merge_conflict_prompt(&local, &remote))
+            }
+        }
+    }
+    merged.join("\n")
+}
+
+// The title to set on the PR, None when it's up to date or was renamed on Github
+fn sync_pr_title(
+    git_branch: &str,
+    directory: &str,
+    pr_data: &GithubPullRequest,
+    ci_mode: bool,
+) -> Option<String> {
+    let branch_config = storage::get_branch_config(git_branch, directory).ok().flatten()?;
+    let commit_message = branch_config.commit_message?;
+    let remote_title = pr_data.title.clone().unwrap_or_default();
+    if let Some(pushed_title) = &branch_config.pushed_pr_title {
+        if *pushed_title != remote_title {
+            info!("The PR title was changed on Github, keeping {}", remote_title);
+            return None;
+        }
+    }
+    let diff_base = diff_base(directory, &base_branch(directory, git_branch));
+    let title = pr_title::pr_title(directory, git_branch, &commit_message, &diff_base, ci_mode);
+    match title == remote_title {
+        true => None,
+        false => Some(title),
+    }
+}
+
+pub fn update_pull_request(
+    directory: &str,
+    git_branch: &str,
+    pr_template: &str,
+    ci_mode: bool,
+) -> Result<(), io::Error> {
     info!("Updating pull request");
 
     let stdout = io::stdout();
     let mut handle = io::BufWriter::new(&stdout);
-    // First, get the PR number for the current branch
-    let cmd_arg = "gh pr view --json number";
+    // First, get the PR number and the state used to tick the checklist for the current branch
+    let cmd_arg = format!("gh pr view --json {}", pr_body::PR_STATE_FIELDS);
     let output = Command::new("sh")
         .arg("-c")
         .arg(cmd_arg)
@@ -601,23 +927,42 @@ pub fn update_pull_request(directory: &str, pr_template: &str) -> Result<(), io:
     }
 
     let pr_json = String::from_utf8_lossy(&output.stdout);
-    let pr_data: serde_json::Value = serde_json::from_str(&pr_json).unwrap_or_default();
+    let pr_data: GithubPullRequest = serde_json::from_str(&pr_json).unwrap_or_default();
 
-    if pr_data["number"].is_null() {
+    let Some(pr_number) = pr_data.number else {
         writeln!(handle, "Could not get PR number").unwrap_or_default();
         let _ = handle.flush();
         process::exit(1);
-    }
+    };
 
-    let pr_number = pr_data["number"].as_u64().unwrap();
+    if text_merge::has_conflict_markers(pr_template) {
+        writeln!(
+            handle,
+            "The PR template still has conflict markers. Resolve them in .commit_message/{}.yaml first.",
+            git_branch
+        )
+        .unwrap_or_default();
+        let _ = handle.flush();
+        process::exit(1);
+    }
+    let merged = merge_pr_body(git_branch, directory, pr_template, &pr_data, ci_mode);
+    let ticked_locally = pr_body::ticked_items(pr_template);
+    let pr_template = stack::with_stack_section(
+        &pr_body::tick_checklist(&pr_body::set_checkboxes(&merged, &ticked_locally), &pr_data),
+        stack::stack_section(directory, git_branch).as_deref(),
+    );
     let pr_template_escaped = pr_template.replace("'", "'\\''");
+    let title = sync_pr_title(git_branch, directory, &pr_data, ci_mode);
 
     info!("{}", pr_template_escaped);
     // Update the PR body
-    let update_cmd = format!(
+    let mut update_cmd = format!(
         r#"gh pr edit {} --body '{}'"#,
         pr_number, pr_template_escaped
     );
+    if let Some(title) = &title {
+        update_cmd.push_str(&format!(" --title '{}'", title.replace("'", "'\\''")));
+    }
 
     info!("Executing command: {}", update_cmd);
     let update_output = Command::new("sh")
@@ -635,6 +980,14 @@ pub fn update_pull_request(directory: &str, pr_template: &str) -> Result<(), io:
         process::exit(1);
     }
 
+    // The merged body is the new local version and the base for the next merge
+    let _ = storage::update_branch_config(git_branch, directory, |config| {
+        config.pr_template = Some(pr_template.clone());
+        config.pushed_pr_body = Some(pr_template.clone());
+        if title.is_some() {
+            config.pushed_pr_title = title.clone();
+        }
+    });
     writeln!(handle, "Pull request #{} updated successfully", pr_number).unwrap_or_default();
     let _ = handle.flush();
 
@@ -0,0 +1,452 @@
+use chrono::prelude::*;
+use clap::ArgMatches;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+    process,
+};
+
+use crate::{
+    branch_utils,
+    commit_message::{self, CommitHeader},
+    storage,
+    utils::git::{git_output, git_result},
+};
+
+pub const KEEP_A_CHANGELOG_FORMAT: &str = "keep-a-changelog";
+pub const RELEASE_NOTES_FORMAT: &str = "release-notes";
+pub const CHANGELOG_FORMATS: [&str; 2] = [KEEP_A_CHANGELOG_FORMAT, RELEASE_NOTES_FORMAT];
+
+const DEFAULT_CHANGELOG_PATH: &str = "CHANGELOG.md";
+const KEEP_A_CHANGELOG_HEADER: &str = "# Changelog\n\nAll notable changes to this project will be documented in this file.\n\nThe format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/).\n";
+
+// Keep a Changelog only has a fixed set of sections, so types are folded into them
+const KEEP_A_CHANGELOG_SECTIONS: [(&str, &[&str]); 3] = [
+    ("Added", &["feat"]),
+    ("Fixed", &["fix"]),
+    ("Changed", &[]),
+];
+
+const RELEASE_NOTES_SECTIONS: [(&str, &[&str]); 10] = [
+    ("Features", &["feat"]),
+    ("Bug Fixes", &["fix"]),
+    ("Performance", &["perf"]),
+    ("Refactoring", &["refactor"]),
+    ("Documentation", &["docs"]),
+    ("Tests", &["test"]),
+    ("Build", &["build"]),
+    ("CI", &["ci"]),
+    ("Reverts", &["revert"]),
+    ("Other", &[]),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ChangelogConfig {
+    // `keep-a-changelog` or `release-notes`
+    pub format: Option<String>,
+    pub path: Option<String>,
+    // Issue tracker URL with an `{id}` placeholder, e.g. `https://app.clickup.com/t/{id}`
+    pub issue_url: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChangelogEntry {
+    pub hash: String,
+    pub header: CommitHeader,
+    pub pr_number: Option<u64>,
+    pub breaking_description: Option<String>,
+}
+
+impl ChangelogEntry {
+    pub fn is_breaking(&self) -> bool {
+        self.header.breaking || self.breaking_description.is_some()
+    }
+}
+
+pub struct ChangelogLinks {
+    pub issue_url: Option<String>,
+    pub repo_url: Option<String>,
+}
+
+pub fn last_tag(directory: &str) -> Option<String> {
+    git_output(directory, "describe --tags --abbrev=0").filter(|t| !t.is_empty())
+}
+
+// Everything since the last tag, or the whole history when there are no tags yet
+pub fn default_range(directory: &str) -> String {
+    match last_tag(directory) {
+        Some(tag) => format!("{}..HEAD", tag),
+        None => "HEAD".to_owned(),
+    }
+}
+
+fn breaking_description(body: &str) -> Option<String> {
+    body.lines()
+        .find_map(|l| {
+            l.trim()
+                .strip_prefix("BREAKING CHANGE:")
+                .or_else(|| l.trim().strip_prefix("BREAKING-CHANGE:"))
+        })
+        .map(|d| d.trim().to_owned())
+}
+
+// Commits that don't follow the commit format are left out
+pub fn commits_in_range(directory: &str, range: &str) -> Vec<ChangelogEntry> {
+    let log = match git_result(
+        directory,
+        &format!("log --no-merges --format='%H%x1f%s%x1f%b%x1e' {}", range),
+    ) {
+        Ok(log) => log,
+        Err(error) => {
+            let stdout = io::stdout();
+            let mut handle = io::BufWriter::new(&stdout);
+            writeln!(
+                handle,
+                "\x1b[1;31mCould not read the commits for {}:\x1b[0m {}",
+                range, error
+            )
+            .unwrap_or_default();
+            let _ = handle.flush();
+            process::exit(1);
+        }
+    };
+
+    let mut entries = vec![];
+    let mut skipped = 0;
+    for record in log.split('\x1e') {
+        let parts: Vec<&str> = record.trim_start_matches('\n').splitn(3, '\x1f').collect();
+        if parts.len() < 2 {
+            continue;
+        }
+        let (subject, pr_number) = commit_message::split_pr_number(parts[1]);
+        match CommitHeader::parse(&subject) {
+            // Release commits only bump versions
+            Some(header) if header.commit_type == "chore" && header.scope.as_deref() == Some("release") => {}
+            Some(header) => entries.push(ChangelogEntry {
+                hash: parts[0].to_owned(),
+                header,
+                pr_number,
+                breaking_description: breaking_description(parts.get(2).unwrap_or(&"")),
+            }),
+            None => skipped += 1,
+        }
+    }
+    info!(
+        "Found {} commits in {}, skipped {} that don't follow the commit format",
+        entries.len(),
+        range,
+        skipped
+    );
+    entries
+}
+
+pub fn changelog_links(directory: &str, config: &ChangelogConfig) -> ChangelogLinks {
+    let repo_url = match branch_utils::get_branch_origin_parts(directory) {
+        Ok(parts) => parts
+            .owner_and_path
+            .map(|p| format!("https://github.com/{}", p)),
+        Err(_) => None,
+    };
+    ChangelogLinks {
+        issue_url: config.issue_url.clone(),
+        repo_url,
+    }
+}
+
+fn issue_link(issue_id: &str, links: &ChangelogLinks) -> String {
+    match (&links.issue_url, &links.repo_url) {
+        (Some(issue_url), _) => format!("[#{}]({})", issue_id, issue_url.replace("{id}", issue_id)),
+        // Github issues are the only tracker we can guess, and they are always numeric
+        (None, Some(repo_url)) if issue_id.chars().all(|c| c.is_ascii_digit()) => {
+            format!("[#{}]({}/issues/{})", issue_id, repo_url, issue_id)
+        }
+        _ => format!("#{}", issue_id),
+    }
+}
+
+fn entry_line(entry: &ChangelogEntry, links: &ChangelogLinks, mark_breaking: bool) -> String {
+    let mut line = "- ".to_owned();
+    if mark_breaking && entry.is_breaking() {
+        line.push_str("**BREAKING:** ");
+    }
+    if let Some(scope) = &entry.header.scope {
+        line.push_str(&format!("**{}:** ", scope));
+    }
+    line.push_str(&entry.header.subject);
+
+    let mut references = vec![];
+    if let Some(issue_id) = &entry.header.issue_id {
+        references.push(issue_link(issue_id, links));
+    }
+    if let Some(pr_number) = entry.pr_number {
+        references.push(match &links.repo_url {
+            Some(repo_url) => format!("[#{}]({}/pull/{})", pr_number, repo_url, pr_number),
+            None => format!("#{}", pr_number),
+        });
+    }
+    if !references.is_empty() {
+        line.push_str(&format!(" ({})", references.join(", ")));
+    }
+    line.push_str(&format!(" ({})", &entry.hash[..entry.hash.len().min(7)]));
+    line
+}
+
+fn section_for<'a>(commit_type: &str, sections: &[(&'a str, &[&str])]) -> &'a str {
+    sections
+        .iter()
+        .find(|(_, types)| types.contains(&commit_type))
+        .or(sections.last())
+        .map(|(name, _)| *name)
+        .unwrap_or("Other")
+}
+
+pub fn section_title(version: Option<&str>, format: &str) -> String {
+    let date = Local::now().format("%Y-%m-%d");
+    match (format, version) {
+        (RELEASE_NOTES_FORMAT, Some(version)) => format!("## {} ({})", version, date),
+        (RELEASE_NOTES_FORMAT, None) => "## Unreleased".to_owned(),
+        (_, Some(version)) => format!("## [{}] - {}", version, date),
+        (_, None) => "## [Unreleased]".to_owned(),
+    }
+}
+
+pub fn render_section(
+    entries: &[ChangelogEntry],
+    version: Option<&str>,
+    format: &str,
+    links: &ChangelogLinks,
+) -> String {
+    let sections: &[(&str, &[&str])] = match format {
+        RELEASE_NOTES_FORMAT => &RELEASE_NOTES_SECTIONS,
+        _ => &KEEP_A_CHANGELOG_SECTIONS,
+    };
+    let mut grouped: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for entry in entries {
+        let section = section_for(&entry.header.commit_type, sections);
+        let position = sections.iter().position(|(name, _)| *name == section).unwrap_or(0);
+        grouped
+            .entry(position)
+            .or_default()
+            .push(entry_line(entry, links, format != RELEASE_NOTES_FORMAT));
+    }
+
+    let mut output_text = format!("{}\n", section_title(version, format));
+    // Release notes list breaking changes first, with their description
+    let breaking_entries: Vec<&ChangelogEntry> = entries.iter().filter(|e| e.is_breaking()).collect();
+    if format == RELEASE_NOTES_FORMAT && !breaking_entries.is_empty() {
+        output_text.push_str("\n### ⚠ Breaking Changes\n\n");
+        for entry in breaking_entries {
+            let mut line = entry_line(entry, links, false);
+            if let Some(description) = &entry.breaking_description {
+                line.push_str(&format!("\n  {}", description));
+            }
+            output_text.push_str(&format!("{}\n", line));
+        }
+    }
+    for (position, lines) in grouped {
+        output_text.push_str(&format!("\n### {}\n\n", sections[position].0));
+        for line in lines {
+            output_text.push_str(&format!("{}\n", line));
+        }
+    }
+    if entries.is_empty() {
+        output_text.push_str("\nNo changes.\n");
+    }
+    output_text
+}
+
+// Replaces the section with the same title, or adds it above the previous releases
+pub fn update_changelog(existing: Option<String>, section: &str, format: &str) -> String {
+    let header = match format {
+        RELEASE_NOTES_FORMAT => "# Changelog\n",
+        _ => KEEP_A_CHANGELOG_HEADER,
+    };
+    let existing = existing.unwrap_or(header.to_owned());
+    let title = section.lines().next().unwrap_or("");
+    let is_release = !title.contains("Unreleased");
+
+    let mut before: Vec<&str> = vec![];
+    let mut after: Vec<&str> = vec![];
+    let mut in_replaced_section = false;
+    let mut found_releases = false;
+    for line in existing.lines() {
+        if line.starts_with("## ") {
+            // A release takes over the commits listed as unreleased
+            in_replaced_section = line == title || (is_release && line.contains("Unreleased"));
+            found_releases = true;
+        }
+        if in_replaced_section {
+            continue;
+        }
+        match found_releases {
+            true => after.push(line),
+            false => before.push(line),
+        }
+    }
+
+    let mut changelog = before.join("\n").trim_end().to_owned();
+    changelog.push_str("\n\n");
+    changelog.push_str(section.trim_end());
+    changelog.push('\n');
+    if !after.is_empty() {
+        changelog.push('\n');
+        changelog.push_str(after.join("\n").trim());
+        changelog.push('\n');
+    }
+    changelog
+}
+
+pub fn changelog_config(directory: &str) -> ChangelogConfig {
+    storage::load_repo_config(directory)
+        .changelog
+        .unwrap_or_default()
+}
+
+pub fn changelog(matches: ArgMatches, directory: &str, git_branch: &str) {
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    let config = changelog_config(directory);
+
+    let range = match (matches.value_of("range"), matches.is_present("branch")) {
+        (Some(x), _) => x.to_owned(),
+        // Only what the branch adds, not what was merged into its base since it forked
+        (None, true) => format!(
+            "{}..HEAD",
+            branch_utils::diff_base(directory, &branch_utils::base_branch(directory, git_branch))
+        ),
+        (None, false) => default_range(directory),
+    };
+    let format = matches
+        .value_of("format")
+        .map(|f| f.to_owned())
+        .or(config.format.clone())
+        .unwrap_or(KEEP_A_CHANGELOG_FORMAT.to_owned());
+    let changelog_path = matches
+        .value_of("output")
+        .map(|p| p.to_owned())
+        .or(config.path.clone())
+        .unwrap_or(DEFAULT_CHANGELOG_PATH.to_owned());
+    let version = matches.value_of("next_version");
+
+    let entries = commits_in_range(directory, &range);
+    let links = changelog_links(directory, &config);
+    let section = render_section(&entries, version, &format, &links);
+
+    if matches.is_present("dry_run") {
+        writeln!(handle, "{}", section).unwrap_or_default();
+        let _ = handle.flush();
+        return;
+    }
+
+    let file_path = Path::new(directory).join(&changelog_path);
+    let updated = update_changelog(fs::read_to_string(&file_path).ok(), &section, &format);
+    match fs::write(&file_path, updated) {
+        Ok(_) => {
+            writeln!(
+                handle,
+                "\x1b[1;32mUpdated {} with {} commits from {}\x1b[0m",
+                changelog_path,
+                entries.len(),
+                range
+            )
+            .unwrap_or_default();
+            let _ = handle.flush();
+        }
+        Err(e) => {
+            writeln!(handle, "\x1b[1;31mCould not write {}:\x1b[0m {}", changelog_path, e)
+                .unwrap_or_default();
+            let _ = handle.flush();
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXISTING: &str = "# Changelog\n\nAll notable changes to this project will be documented in this file.\n\n## [Unreleased]\n\n### Added\n\n- old unreleased entry\n\n## [0.1.0] - 2024-01-01\n\n### Added\n\n- first release\n";
+
+    fn entry(header: &str, body: &str) -> ChangelogEntry {
+        ChangelogEntry {
+            hash: "abcdef1234567".to_owned(),
+            header: CommitHeader::parse(header).unwrap(),
+            pr_number: None,
+            breaking_description: breaking_description(body),
+        }
+    }
+
+    #[test]
+    fn breaking_change_footer() {
+        let body =
+            "Some context.\n\nBREAKING CHANGE: the config moved to .commit_message/\nRefs #12";
+        assert_eq!(
+            breaking_description(body),
+            Some("the config moved to .commit_message/".to_owned())
+        );
+        assert_eq!(
+            breaking_description("BREAKING-CHANGE: tokens are required"),
+            Some("tokens are required".to_owned())
+        );
+        assert_eq!(breaking_description("Refs #12"), None);
+
+        let footer_only = entry("feat: move the config", body);
+        assert!(!footer_only.header.breaking);
+        assert!(footer_only.is_breaking());
+        assert!(entry("feat!: move the config", "").is_breaking());
+        assert!(!entry("feat: move the config", "").is_breaking());
+    }
+
+    #[test]
+    fn release_notes_list_breaking_changes_first() {
+        let links = ChangelogLinks {
+            issue_url: None,
+            repo_url: None,
+        };
+        let entries = vec![
+            entry("fix: handle empty diffs", ""),
+            entry("feat: move the config", "BREAKING CHANGE: the config moved"),
+        ];
+        let section = render_section(&entries, None, RELEASE_NOTES_FORMAT, &links);
+        let breaking = section.find("Breaking Changes").unwrap();
+        assert!(breaking < section.find("move the config").unwrap());
+        assert!(section.contains("\n  the config moved\n"));
+    }
+
+    #[test]
+    fn release_replaces_unreleased_section() {
+        let section = "## [0.2.0] - 2024-02-01\n\n### Fixed\n\n- new fix\n";
+        let changelog =
+            update_changelog(Some(EXISTING.to_owned()), section, KEEP_A_CHANGELOG_FORMAT);
+        assert!(!changelog.contains("[Unreleased]"));
+        assert!(!changelog.contains("old unreleased entry"));
+        assert!(changelog.starts_with("# Changelog\n\nAll notable changes"));
+        let new_release = changelog.find("## [0.2.0]").unwrap();
+        let old_release = changelog.find("## [0.1.0]").unwrap();
+        assert!(new_release < old_release);
+        assert!(changelog.ends_with("- first release\n"));
+    }
+
+    #[test]
+    fn unreleased_section_is_replaced_in_place() {
+        let section = "## [Unreleased]\n\n### Added\n\n- new unreleased entry\n";
+        let changelog =
+            update_changelog(Some(EXISTING.to_owned()), section, KEEP_A_CHANGELOG_FORMAT);
+        assert_eq!(changelog.matches("## [Unreleased]").count(), 1);
+        assert!(changelog.contains("- new unreleased entry"));
+        assert!(!changelog.contains("old unreleased entry"));
+        assert!(changelog.find("## [Unreleased]").unwrap() < changelog.find("## [0.1.0]").unwrap());
+    }
+
+    #[test]
+    fn new_changelog_gets_the_format_header() {
+        let section = "## [Unreleased]\n\n### Added\n\n- entry\n";
+        let changelog = update_changelog(None, section, KEEP_A_CHANGELOG_FORMAT);
+        assert!(changelog.starts_with(KEEP_A_CHANGELOG_HEADER));
+        assert!(changelog.ends_with("- entry\n"));
+    }
+}
@@ -0,0 +1,346 @@
+use log::{debug, info};
+use regex::Regex;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+    process::Command,
+};
+
+use crate::{pr_metadata::PrMetadata, prompts};
+
+// Where Github looks for the CODEOWNERS file, in order
+pub const CODEOWNERS_PATHS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+pub const REQUEST_REVIEWERS: &str = "request";
+pub const SUGGEST_REVIEWERS: &str = "suggest";
+pub const NO_REVIEWERS: &str = "off";
+
+#[derive(Debug, Clone)]
+pub struct OwnershipRule {
+    pub pattern: String,
+    pub owners: Vec<String>,
+    regex: Regex,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReviewerSuggestion {
+    // Reviewer -> files they cover that no earlier reviewer covered
+    pub reviewers: Vec<(String, Vec<String>)>,
+    // Files without owners, or only owned by the author
+    pub uncovered: Vec<String>,
+}
+
+// Gitignore style patterns: `*` stays within a directory, `**` crosses them and patterns
+// with a slash before the end are relative to the repository root
+fn pattern_regex(pattern: &str) -> Option<Regex> {
+    let anchored = pattern.starts_with('/') || pattern.trim_end_matches('/').contains('/');
+    let mut body = pattern.trim_start_matches('/').to_owned();
+    if body.ends_with('/') {
+        body.push_str("**");
+    }
+    let mut regex = String::from("^");
+    if !anchored {
+        regex.push_str("(?:.*/)?");
+    }
+    let chars: Vec<char> = body.chars().collect();
+    let mut index = 0;
+    while index < chars.len() {
+        match chars[index] {
+            '*' if chars.get(index + 1) == Some(&'*') => {
+                if chars.get(index + 2) == Some(&'/') {
+                    regex.push_str("(?:.*/)?");
+                    index += 3;
+                } else {
+                    regex.push_str(".*");
+                    index += 2;
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        index += 1;
+    }
+    // A plain path can be a directory that owns everything in it. A wildcard in the last
+    // segment only matches that level, so `docs/*` doesn't own `docs/a/b.md`
+    let last_segment = body.rsplit('/').next().unwrap_or(&body);
+    if !last_segment.contains(['*', '?']) {
+        regex.push_str("(?:/.*)?");
+    }
+    regex.push('$');
+    Regex::new(&regex).ok()
+}
+
+pub fn parse_codeowners(content: &str) -> Vec<OwnershipRule> {
+    content
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next()?.to_owned();
+            let owners = parts
+                .take_while(|p| !p.starts_with('#'))
+                .map(|p| p.to_owned())
+                .collect();
+            let regex = pattern_regex(&pattern)?;
+            Some(OwnershipRule {
+                pattern,
+                owners,
+                regex,
+            })
+        })
+        .collect()
+}
+
+pub fn load_codeowners(directory: &str) -> Option<Vec<OwnershipRule>> {
+    CODEOWNERS_PATHS.iter().find_map(|path| {
+        fs::read_to_string(Path::new(directory).join(path))
+            .ok()
+            .map(|content| {
+                info!("Using code owners from {}", path);
+                parse_codeowners(&content)
+            })
+    })
+}
+
+// The last matching rule wins, like on Github. A rule without owners unsets them
+pub fn file_owners<'a>(rules: &'a [OwnershipRule], file: &str) -> &'a [String] {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.regex.is_match(file))
+        .map(|rule| rule.owners.as_slice())
+        .unwrap_or(&[])
+}
+
+// Reviewers as `gh pr create --reviewer` expects them, emails can't be requested
+fn reviewer_login(owner: &str) -> Option<String> {
+    match owner.contains('@') && !owner.starts_with('@') {
+        true => None,
+        false => Some(owner.trim_start_matches('@').to_owned()),
+    }
+}
+
+// Greedy set cover: each file needs one of its owners, keep picking the owner
+// covering most of the remaining files
+pub fn suggest_reviewers(
+    rules: &[OwnershipRule],
+    files: &[String],
+    author: Option<&str>,
+) -> ReviewerSuggestion {
+    let mut remaining: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut uncovered = vec![];
+    for file in files.iter().filter(|f| !f.is_empty()) {
+        let owners: Vec<String> = file_owners(rules, file)
+            .iter()
+            .filter_map(|o| reviewer_login(o))
+            .filter(|o| Some(o.as_str()) != author)
+            .collect();
+        match owners.is_empty() {
+            true => uncovered.push(file.clone()),
+            false => {
+                remaining.insert(file, owners);
+            }
+        }
+    }
+
+    let mut reviewers = vec![];
+    while !remaining.is_empty() {
+        let mut counts: BTreeMap<&String, usize> = BTreeMap::new();
+        for owners in remaining.values() {
+            for owner in owners {
+                *counts.entry(owner).or_default() += 1;
+            }
+        }
+        let Some((best, _)) = counts
+            .into_iter()
+            .max_by(|(a_owner, a), (b_owner, b)| a.cmp(b).then(b_owner.cmp(a_owner)))
+        else {
+            break;
+        };
+        let best = best.clone();
+        let covered: Vec<String> = remaining
+            .iter()
+            .filter(|(_, owners)| owners.contains(&best))
+            .map(|(file, _)| file.to_string())
+            .collect();
+        remaining.retain(|_, owners| !owners.contains(&best));
+        reviewers.push((best, covered));
+    }
+    debug!("Reviewers {:?}, uncovered {:?}", reviewers, uncovered);
+    ReviewerSuggestion {
+        reviewers,
+        uncovered,
+    }
+}
+
+pub fn current_github_login(directory: &str) -> Option<String> {
+    let cmd_arg = format!("cd {} && gh api user --jq .login", directory);
+    let output = Command::new("sh").arg("-c").arg(cmd_arg).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_owned()).filter(|l| !l.is_empty())
+}
+
+// Adds the code owners of the branch files to the reviewers, asking first unless `mode` is
+// `request`. In CI mode suggestions are only shown
+pub fn apply_reviewer_suggestion(
+    directory: &str,
+    files: &[String],
+    mode: &str,
+    ci_mode: bool,
+    metadata: &mut PrMetadata,
+) {
+    if mode == NO_REVIEWERS {
+        return;
+    }
+    let Some(rules) = load_codeowners(directory) else {
+        return;
+    };
+    let author = current_github_login(directory);
+    let suggestion = suggest_reviewers(&rules, files, author.as_deref());
+
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    for (reviewer, covered) in &suggestion.reviewers {
+        writeln!(
+            handle,
+            "\x1b[1;1m{}\x1b[0m owns {} changed file(s): {}",
+            reviewer,
+            covered.len(),
+            covered.join(", ")
+        )
+        .unwrap_or_default();
+    }
+    if !suggestion.uncovered.is_empty() {
+        writeln!(
+            handle,
+            "\x1b[1;33mNo code owners for:\x1b[0m {}",
+            suggestion.uncovered.join(", ")
+        )
+        .unwrap_or_default();
+    }
+    let _ = handle.flush();
+
+    let suggested: Vec<String> = suggestion.reviewers.into_iter().map(|(r, _)| r).collect();
+    let selected = match (mode, ci_mode) {
+        (REQUEST_REVIEWERS, _) => suggested,
+        (_, true) => vec![],
+        _ => prompts::reviewers_prompt(suggested),
+    };
+    for reviewer in selected {
+        if !metadata.reviewers.contains(&reviewer) {
+            metadata.reviewers.push(reviewer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, file: &str) -> bool {
+        pattern_regex(pattern).unwrap().is_match(file)
+    }
+
+    fn files(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn anchored_directory() {
+        assert!(matches("/build/", "build/out.txt"));
+        assert!(matches("/build/", "build/logs/out.txt"));
+        assert!(!matches("/build/", "src/build/out.txt"));
+        // Without the leading slash, the directory can be anywhere
+        assert!(matches("build/", "src/build/out.txt"));
+    }
+
+    #[test]
+    fn extension_anywhere() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(matches("*.rs", "src/utils/git.rs"));
+        assert!(!matches("*.rs", "src/main.rs.orig"));
+        assert!(!matches("*.rs", "README.md"));
+    }
+
+    #[test]
+    fn wildcard_stays_in_its_directory() {
+        assert!(matches("docs/*", "docs/a.md"));
+        assert!(!matches("docs/*", "docs/a/b.md"));
+        // A plain directory owns everything below it
+        assert!(matches("docs", "docs/a/b.md"));
+        assert!(matches("/docs", "docs/a/b.md"));
+    }
+
+    #[test]
+    fn double_star_crosses_directories() {
+        assert!(matches("docs/**", "docs/a/b.md"));
+        assert!(matches("**/logs", "deep/nested/logs/today.log"));
+        assert!(matches("**/logs", "logs/today.log"));
+        assert!(matches("src/**/test.rs", "src/test.rs"));
+        assert!(matches("src/**/test.rs", "src/a/b/test.rs"));
+        assert!(!matches("src/**/test.rs", "lib/a/test.rs"));
+    }
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let rules = parse_codeowners(
+            "# Owners\n* @everyone\n*.rs @rustaceans # inline comment\n/src/generated/\n",
+        );
+        assert_eq!(rules.len(), 3);
+        assert_eq!(file_owners(&rules, "README.md"), ["@everyone"]);
+        assert_eq!(file_owners(&rules, "src/main.rs"), ["@rustaceans"]);
+        // A later rule without owners unsets them
+        assert!(file_owners(&rules, "src/generated/types.rs").is_empty());
+    }
+
+    #[test]
+    fn greedy_owner_cover() {
+        let rules = parse_codeowners(
+            "src/a.rs @alice @bob\nsrc/b.rs @bob\nsrc/c.rs @carol @bob\ndocs/ @dave\nnotes/ owner@example.com\n",
+        );
+        let changed = files(&[
+            "src/a.rs",
+            "src/b.rs",
+            "src/c.rs",
+            "docs/x.md",
+            "notes/y.md",
+            "z.txt",
+        ]);
+
+        let suggestion = suggest_reviewers(&rules, &changed, None);
+        assert_eq!(
+            suggestion.reviewers,
+            vec![
+                (
+                    "bob".to_owned(),
+                    files(&["src/a.rs", "src/b.rs", "src/c.rs"])
+                ),
+                ("dave".to_owned(), files(&["docs/x.md"])),
+            ]
+        );
+        // Emails can't be requested as reviewers
+        assert_eq!(suggestion.uncovered, files(&["notes/y.md", "z.txt"]));
+
+        // The author is never suggested, their files go to the other owners
+        let suggestion = suggest_reviewers(&rules, &changed, Some("bob"));
+        assert_eq!(
+            suggestion.reviewers,
+            vec![
+                ("alice".to_owned(), files(&["src/a.rs"])),
+                ("carol".to_owned(), files(&["src/c.rs"])),
+                ("dave".to_owned(), files(&["docs/x.md"])),
+            ]
+        );
+        assert_eq!(
+            suggestion.uncovered,
+            files(&["src/b.rs", "notes/y.md", "z.txt"])
+        );
+    }
+}
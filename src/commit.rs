@@ -1,13 +1,19 @@
 use crate::branch_utils;
+use crate::commit_message::{self, CommitHeader};
+use crate::prompt_templates;
 use crate::prompts;
+use crate::scopes;
+use crate::split_commit;
 use crate::storage;
 use crate::utils::claude;
+use crate::utils::commit_summary;
 use crate::ux_utils;
 use clap::ArgMatches;
 use indicatif::ProgressBar;
 use inquire::Confirm;
 use log::debug;
-use log::{error, info, warn};
+use log::{info, warn};
+use std::collections::HashMap;
 use std::process::Command;
 use std::time::Duration;
 use std::{
@@ -52,8 +58,12 @@ pub async fn commit(
 
     let mut commit_message = None;
     let mut pr_template = None;
+    let mut issue_labels: Vec<String> = vec![];
     match stored_pr_template {
         Some(x) => {
+            if let Some(labels) = x.issue_labels {
+                issue_labels = labels;
+            }
             if x.commit_message.is_some() {
                 commit_message = x.commit_message
             }
@@ -68,6 +78,9 @@ pub async fn commit(
     let team_prefix = "INF";
 
     let mut additional_commit_message = vec![];
+    let configured_scopes = scopes::configured_scopes(directory);
+    let mut commit_scopes: Vec<String> = vec![];
+    let mut split_commits_done = false;
     info!(
         "use_claude: {}, commit_message {:?}",
         use_claude, commit_message
@@ -89,34 +102,19 @@ pub async fn commit(
             let git_diff_stdout_string = str::from_utf8(&git_diff.stdout).unwrap();
             info!("Git diff: {:?}", git_diff_stdout_string);
 
-            let cmd_arg = format!(
-                r#"cd {} && claude --model sonnet -p "We have done several changes to this repository. You are a technical writer in charge of documenting the changes and writing the changes to a document.
-The changes are the following: {}
-Please analyze this info and return a json object with the following structure: commit_message: string, commit_type: string, commit_labels: string[],
-The expected values are the following.
-**commit_message**
-should be a string under 50 characters that summarizes the main changes to the pr. Do not include the commit type in the commit_message entry
-**commit_type**
-as a string choose the best from the following options and return one of these keys:
-feat: A new feature,
-fix: Bug feature related or code linting, typecheck, etc fixes,
-test: Adding missing tests or correcting existing tests,
-refactor: A code change that improves performance or code quality,
-docs: Documentation only changes,
-build: Changes that affect the build system or external dependencies example scopes: gulp, broccoli, npm, ci: Changes to our CI configuration files and scripts example scopes: Travis, Circle, BrowserStack, SauceLabs, revert: Reverts a previous commit,
-**commit_labels**
-an array of strings; choose all that apply from the web, api or ci; if none match return an empty array - web: files related to frontend code - api: files related to backend code - ci: files related to deployments.
-Only output the result, do not commit the message" --output-format json"#,
-                &directory, &git_diff_stdout_string
+            let prompt_text = prompt_templates::render_prompt(
+                directory,
+                prompt_templates::COMMIT_MESSAGE_TEMPLATE,
+                &HashMap::from([
+                    ("diff", git_diff_stdout_string.to_owned()),
+                    ("allowed_types", prompt_templates::allowed_types_variable()),
+                    ("scopes", scopes::scopes_prompt_variable(&configured_scopes)),
+                ]),
             );
-            info!("Claude command: {:?}", cmd_arg);
             let bar = ProgressBar::new_spinner();
             bar.enable_steady_tick(Duration::from_millis(100));
-            let output = Command::new("sh")
-                .arg("-c")
-                .arg(cmd_arg)
-                .output()
-                .expect("Failed to run  process");
+            let output =
+                claude::run_claude_prompt(directory, &prompt_text).expect("Failed to run  process");
 
             bar.finish();
 
@@ -129,29 +127,77 @@ Only output the result, do not commit the message" --output-format json"#,
             let result_json = claude::parse_claude_response(result_stdout_string)
                 .ok()
                 .expect("Should return JSON");
-            commit_message = Some(format!(
-                "{}: {} [{}] #{}",
-                result_json
-                    .get("commit_type")
-                    .unwrap()
-                    .as_str()
-                    .unwrap()
-                    .to_lowercase(),
-                result_json
-                    .get("commit_message")
-                    .unwrap()
-                    .as_str()
-                    .unwrap()
-                    .to_lowercase()
-                    .chars()
-                    .take(50)
-                    .into_iter()
-                    .collect::<String>(),
-                team_prefix,
-                issue_id
-            ));
+            let commit_labels: Vec<String> = result_json
+                .get("commit_labels")
+                .and_then(|l| l.as_array())
+                .map(|l| {
+                    l.iter()
+                        .filter_map(|x| x.as_str())
+                        .map(|x| x.to_lowercase())
+                        .collect()
+                })
+                .unwrap_or_default();
+            info!("Claude commit labels: {:?}", commit_labels);
+            let mut inferred_scopes = scopes::infer_scopes(
+                &configured_scopes,
+                &branch_utils::changed_file_names(directory),
+            );
+            for label in commit_labels {
+                if !inferred_scopes.contains(&label) {
+                    inferred_scopes.push(label);
+                }
+            }
+            let commit_scope = match matches.value_of("scope") {
+                Some(scope) => Some(scope.to_owned()),
+                None => inferred_scopes.first().cloned(),
+            };
+            commit_scopes = inferred_scopes;
+            let claude_commit_message: String = result_json
+                .get("commit_message")
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .chars()
+                .take(50)
+                .collect();
+            commit_message = Some(
+                CommitHeader::new(
+                    result_json.get("commit_type").unwrap().as_str().unwrap(),
+                    commit_scope,
+                    &claude_commit_message,
+                    team_prefix,
+                    &issue_id,
+                )
+                .format(),
+            );
         }
         _ => {
+            // A branch without commits since it forked from its base is starting something new
+            let base = branch_utils::base_branch(directory, git_branch);
+            let is_first_commit = branch_utils::branch_commit_count(
+                directory,
+                &branch_utils::diff_base(directory, &base),
+            )
+            .map_or(is_new_branch, |count| count == 0);
+            info!(
+                "Is new branch: {}, first commit since {}: {}",
+                &is_new_branch, base, is_first_commit
+            );
+            let type_suggestion =
+                branch_utils::find_changed_file_types(directory, &is_first_commit, &issue_labels);
+            info!("Used types: {:?}", &type_suggestion.used_types);
+            let suggestion = commit_summary::suggest_commit(
+                directory,
+                git_branch,
+                type_suggestion.commit_type.clone(),
+            );
+            let proposed_type = suggestion.as_ref().and_then(|s| s.commit_type.clone());
+            info!("Proposed types: {:?}", &proposed_type);
+            let proposed_scope = match matches.value_of("scope") {
+                Some(scope) => Some(scope.to_owned()),
+                None => suggestion.as_ref().and_then(|s| s.scope.clone()),
+            };
+
             let message_name;
             if matches.is_present("message") {
                 message_name = matches
@@ -160,11 +206,10 @@ Only output the result, do not commit the message" --output-format json"#,
                     .to_string()
                     .to_lowercase();
             } else {
-                message_name = branch_utils::branch_name(&git_branch)
-                    .to_lowercase()
-                    .chars()
-                    .take(55)
-                    .collect::<String>();
+                message_name = suggestion
+                    .as_ref()
+                    .map(|s| s.subject.to_lowercase())
+                    .unwrap_or_default();
             };
 
             let mut can_build_default_message = true;
@@ -189,6 +234,30 @@ Only output the result, do not commit the message" --output-format json"#,
                 can_build_default_message = false;
                 output_text.push_str(&format!("\x1b[1;31m- No message name found\x1b[0m\n"));
             }
+            if let Some(commit_type) = &proposed_type {
+                output_text.push_str("- Proposed type is ");
+                output_text.push_str(&format!(
+                    "\x1b[1;1m{}\x1b[0m (confidence {:.0}%)\n",
+                    commit_type,
+                    type_suggestion.confidence * 100.0
+                ));
+            }
+            if let Some(scope) = &proposed_scope {
+                output_text.push_str("- Scope is ");
+                output_text.push_str(&format!("\x1b[1;1m{}\x1b[0m\n", scope));
+            }
+            if let Some(stats_subject) = suggestion
+                .as_ref()
+                .and_then(|s| s.stats_subject.as_ref())
+                .filter(|subject| **subject != message_name)
+            {
+                output_text.push_str("- Message from staged changes is ");
+                output_text.push_str(&format!("\x1b[1;1m{}\x1b[0m\n", stats_subject));
+            }
+            if let Some(suggestion) = &suggestion {
+                output_text.push_str("- Staged changes: ");
+                output_text.push_str(&format!("\x1b[1;1m{}\x1b[0m\n", suggestion.summary));
+            }
             if is_new_branch == false {
                 let _ = storage::load_branch_config(
                     &git_branch,
@@ -199,28 +268,44 @@ Only output the result, do not commit the message" --output-format json"#,
                     has_gh,
                 );
             }
-            info!("Is new branch: {}", &is_new_branch);
-            let (proposed_type, used_types) =
-                branch_utils::find_changed_file_types(directory, &is_new_branch);
-            info!("Proposed types: {:?}", &proposed_type);
             if proposed_type.is_none() {
                 can_build_default_message = false;
             }
-            info!("Used types: {:?}", &used_types);
             writeln!(handle, "{}", output_text).unwrap_or_default();
 
             warn!("Can build message: {}", &can_build_default_message);
             let _ = handle.flush();
             let mut will_accept_suggested_message = false;
+            if split_commit::should_split_prompt(type_suggestion.used_types, ci_mode) {
+                let split_context = split_commit::SplitContext {
+                    directory,
+                    git_branch,
+                    is_new_branch: is_first_commit,
+                    issue_labels: &issue_labels,
+                    message_name: &message_name,
+                    team_prefix,
+                    issue_id: &issue_id,
+                    no_verify,
+                };
+                match split_commit::split_and_commit(&split_context, pr_template.clone()) {
+                    Some(last_commit_message) => {
+                        commit_message = Some(last_commit_message);
+                        split_commits_done = true;
+                    }
+                    None => process::exit(0),
+                }
+                can_build_default_message = false;
+                will_accept_suggested_message = true;
+            }
             if can_build_default_message {
-                let mut proposed_output_string: String = "".to_owned();
-                proposed_output_string.push_str(&format!(
-                    "{}: {} [{}] #{}",
+                let proposed_output_string = CommitHeader::new(
                     &proposed_type.clone().unwrap(),
+                    proposed_scope.clone(),
                     &message_name,
-                    &team_prefix,
-                    &issue_id
-                ));
+                    team_prefix,
+                    &issue_id,
+                )
+                .format();
                 info!("Will propose default message: {}", &proposed_output_string);
                 let mut proposed_ouput_message = "".to_owned();
                 proposed_ouput_message.push_str(
@@ -234,6 +319,7 @@ Only output the result, do not commit the message" --output-format json"#,
 
                 if ci_mode == true {
                     commit_message = Some(proposed_output_string);
+                    commit_scopes = proposed_scope.clone().into_iter().collect();
                     will_accept_suggested_message = true;
                 } else {
                     let confimation_prompt =
@@ -244,6 +330,7 @@ Only output the result, do not commit the message" --output-format json"#,
                     will_accept_suggested_message = match confimation_prompt {
                         Ok(selection) => {
                             commit_message = Some(proposed_output_string);
+                            commit_scopes = proposed_scope.clone().into_iter().collect();
                             selection
                         }
 
@@ -263,21 +350,33 @@ Only output the result, do not commit the message" --output-format json"#,
                 let selected_type = prompts::select_types_prompt(proposed_type);
                 info!("Selected type: {}", selected_type);
 
-                let _scope_options: Vec<&str> = vec![
-                    "web: Work related to front end",
-                    "api: work related to the back end",
-                    "devops: work related to infrastructure, tools, etc.",
-                ];
+                let selected_scope = prompts::select_scope_prompt(
+                    scopes::scope_names(&configured_scopes),
+                    proposed_scope.clone(),
+                );
+                info!("Selected scope: {:?}", selected_scope);
 
-                let message = prompts::select_message_prompt(&git_branch);
-                output_string.push_str(&format!(
-                    // "\x1b[1;32m{}: {} [{}] #{}\x1b[1;0m",
-                    "{}: {} [{}] #{}",
-                    selected_type,
-                    message.to_lowercase(),
-                    selected_team_prefix,
-                    issue_id
-                ));
+                let mut message_options: Vec<String> = vec![];
+                if !message_name.is_empty() {
+                    message_options.push(message_name.clone());
+                }
+                if let Some(stats_subject) = suggestion.as_ref().and_then(|s| s.stats_subject.clone())
+                {
+                    if !message_options.contains(&stats_subject) {
+                        message_options.push(stats_subject);
+                    }
+                }
+                let message = prompts::select_message_prompt(&message_name, message_options);
+                output_string.push_str(
+                    &CommitHeader::new(
+                        &selected_type,
+                        selected_scope.clone(),
+                        &message,
+                        &selected_team_prefix,
+                        &issue_id,
+                    )
+                    .format(),
+                );
                 let additional_message = prompts::select_additional_message_prompt();
                 if additional_message.is_some() {
                     info!("Additional message: {:?}", &additional_message);
@@ -287,26 +386,7 @@ Only output the result, do not commit the message" --output-format json"#,
                     }
                 }
 
-                // Add scope
-                if matches.is_present("scope") {
-                    let scope = matches.value_of("scope").unwrap();
-                    output_string.push_str("(");
-                    output_string.push_str(scope);
-                    output_string.push_str(")");
-                }
-
-                // Add message
-                if matches.is_present("message") {
-                    match matches.value_of("message") {
-                        Some(message) => {
-                            output_string.push_str(message);
-                        }
-                        None => {
-                            error!("No scope defined");
-                            return;
-                        }
-                    };
-                }
+                commit_scopes = selected_scope.into_iter().collect();
                 commit_message = Some(output_string.clone());
                 if additional_commit_message.len() > 0 {
                     output_string.push_str("\x1b[1;32m\n");
@@ -321,26 +401,54 @@ Only output the result, do not commit the message" --output-format json"#,
         }
     }
 
-    info!("Will ask for commit");
-    let commit_message_str = commit_message.clone().unwrap();
-    writeln!(handle, "Proposed commit is {}", commit_message_str).unwrap_or_default();
-    let _ = handle.flush();
-    let will_commit_pr = prompts::commit_pr_prompt(Some(commit_message_str));
-
-    info!("Commit was defined: {}", will_commit_pr);
-    if will_commit_pr == true {
-        ux_utils::commit_and_push(
+    if split_commits_done {
+        let push_context = ux_utils::PushContext {
             directory,
-            commit_message_str,
-            additional_commit_message.clone(),
-            &git_branch,
-            pr_template.clone(),
+            git_branch,
+            pr_template: pr_template.clone(),
             no_verify,
             ci_mode,
             github_api_token,
             has_gh,
-        )
-        .await;
+        };
+        ux_utils::push_after_commit(push_context, commit_message.clone().unwrap()).await;
+    } else {
+        info!("Will ask for commit");
+        let footers = commit_message::build_footers(
+            directory,
+            git_branch,
+            &issue_id,
+            matches.value_of("breaking").map(|x| x.to_owned()),
+            !ci_mode,
+        );
+        let commit_message_str = commit_message::apply_footers(
+            &commit_message.clone().unwrap(),
+            &mut additional_commit_message,
+            &footers,
+        );
+        writeln!(handle, "Proposed commit is {}", commit_message_str).unwrap_or_default();
+        for line in &footers.lines {
+            writeln!(handle, "  \x1b[1;32m{}\x1b[1;0m", line).unwrap_or_default();
+        }
+        let _ = handle.flush();
+        let will_commit_pr = prompts::commit_pr_prompt(Some(&commit_message_str));
+
+        info!("Commit was defined: {}", will_commit_pr);
+        if will_commit_pr {
+            scopes::add_branch_scopes(git_branch, directory, &commit_scopes);
+            ux_utils::commit_and_push(
+                directory,
+                commit_message_str,
+                additional_commit_message.clone(),
+                git_branch,
+                pr_template.clone(),
+                no_verify,
+                ci_mode,
+                github_api_token,
+                has_gh,
+            )
+            .await;
+        }
     }
     let mut confirm_message = "Do you want to build a PR template?".to_owned();
     if use_claude {
@@ -0,0 +1,247 @@
+use lazy_static::lazy_static;
+use log::info;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{prompts, storage, utils::git::git_output};
+
+lazy_static! {
+    static ref HEADER_REGEX: Regex = Regex::new(
+        r"^(?P<type>[A-Za-z]+)(?:\((?P<scope>[^)]*)\))?(?P<breaking>!)?: (?P<subject>.*?)(?: \[(?P<prefix>[^\]]+)\])?(?: #(?P<issue>\S+))?$"
+    )
+    .unwrap();
+    static ref PR_SUFFIX_REGEX: Regex = Regex::new(r"^(?P<line>.*?)\s*\(#(?P<number>\d+)\)$").unwrap();
+}
+
+// Longest subject the commit message prompt and the PR title accept
+pub const MAX_SUBJECT_LENGTH: usize = 55;
+
+// `type(scope)!: subject [PREFIX] #id`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitHeader {
+    pub commit_type: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub subject: String,
+    pub team_prefix: Option<String>,
+    pub issue_id: Option<String>,
+}
+
+impl CommitHeader {
+    pub fn new(
+        commit_type: &str,
+        scope: Option<String>,
+        subject: &str,
+        team_prefix: &str,
+        issue_id: &str,
+    ) -> CommitHeader {
+        CommitHeader {
+            commit_type: commit_type.to_lowercase(),
+            scope: scope.filter(|s| !s.is_empty()),
+            breaking: false,
+            subject: subject.to_lowercase(),
+            team_prefix: Some(team_prefix.to_owned()).filter(|s| !s.is_empty()),
+            issue_id: Some(issue_id.to_owned()).filter(|s| !s.is_empty()),
+        }
+    }
+
+    pub fn parse(header: &str) -> Option<CommitHeader> {
+        let first_line = header.lines().next().unwrap_or("").trim();
+        let caps = HEADER_REGEX.captures(first_line)?;
+        Some(CommitHeader {
+            commit_type: caps.name("type")?.as_str().to_lowercase(),
+            scope: caps.name("scope").map(|s| s.as_str().to_owned()),
+            breaking: caps.name("breaking").is_some(),
+            subject: caps.name("subject")?.as_str().to_owned(),
+            team_prefix: caps.name("prefix").map(|s| s.as_str().to_owned()),
+            issue_id: caps.name("issue").map(|s| s.as_str().to_owned()),
+        })
+    }
+
+    pub fn format(&self) -> String {
+        let mut header = self.commit_type.clone();
+        if let Some(scope) = &self.scope {
+            header.push_str(&format!("({})", scope));
+        }
+        if self.breaking {
+            header.push('!');
+        }
+        header.push_str(&format!(": {}", self.subject));
+        if let Some(team_prefix) = &self.team_prefix {
+            header.push_str(&format!(" [{}]", team_prefix));
+        }
+        if let Some(issue_id) = &self.issue_id {
+            header.push_str(&format!(" #{}", issue_id));
+        }
+        header
+    }
+}
+
+// Squash merges append ` (#123)` to the header, which isn't part of the tool's format
+pub fn split_pr_number(header: &str) -> (String, Option<u64>) {
+    let first_line = header.lines().next().unwrap_or("").trim();
+    match PR_SUFFIX_REGEX.captures(first_line) {
+        Some(caps) => (
+            caps.name("line").unwrap().as_str().to_owned(),
+            caps.name("number").unwrap().as_str().parse().ok(),
+        ),
+        None => (first_line.to_owned(), None),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CommitFootersConfig {
+    // Ask whether the commit is a breaking change. Defaults to true
+    pub breaking_changes: Option<bool>,
+    // `Refs` or `Closes`; no trailer when unset
+    pub issue_trailer: Option<String>,
+    pub co_authors: Option<bool>,
+    pub signed_off_by: Option<bool>,
+}
+
+pub fn current_author(directory: &str) -> Option<String> {
+    let name = git_output(directory, "config user.name").unwrap_or_default();
+    let email = git_output(directory, "config user.email").unwrap_or_default();
+    if name.is_empty() || email.is_empty() {
+        return None;
+    }
+    Some(format!("{} <{}>", name, email))
+}
+
+pub fn recent_authors(directory: &str) -> Vec<String> {
+    let current = current_author(directory);
+    let mut authors: Vec<String> = vec![];
+    for author in git_output(directory, "log -n 300 --format='%an <%ae>'")
+        .unwrap_or_default()
+        .lines()
+    {
+        let author = author.trim().to_owned();
+        if author.is_empty() || Some(&author) == current.as_ref() || authors.contains(&author) {
+            continue;
+        }
+        authors.push(author);
+    }
+    authors
+}
+
+fn issue_for_trailer(directory: &str, git_branch: &str, issue_id: &str) -> Option<String> {
+    let stored_issue_id = match storage::get_branch_config(git_branch, directory) {
+        Ok(Some(config)) => config.issue_id,
+        _ => None,
+    };
+    stored_issue_id
+        .or(Some(issue_id.to_owned()))
+        .filter(|id| !id.is_empty())
+}
+
+pub struct CommitFooters {
+    pub breaking: bool,
+    pub lines: Vec<String>,
+}
+
+// Trailers are returned as a single paragraph so git recognises them
+pub fn build_footers(
+    directory: &str,
+    git_branch: &str,
+    issue_id: &str,
+    breaking_change: Option<String>,
+    interactive: bool,
+) -> CommitFooters {
+    let config = storage::load_repo_config(directory)
+        .commit_footers
+        .unwrap_or_default();
+    let mut footers = CommitFooters {
+        breaking: false,
+        lines: vec![],
+    };
+
+    let breaking_description = match breaking_change {
+        Some(x) => Some(x),
+        None if interactive && config.breaking_changes.unwrap_or(true) => {
+            prompts::breaking_change_prompt()
+        }
+        None => None,
+    };
+    if let Some(description) = breaking_description {
+        footers.breaking = true;
+        footers
+            .lines
+            .push(format!("BREAKING CHANGE: {}", description.trim()));
+    }
+
+    if let Some(trailer) = &config.issue_trailer {
+        if let Some(id) = issue_for_trailer(directory, git_branch, issue_id) {
+            footers.lines.push(format!("{}: #{}", trailer, id));
+        }
+    }
+
+    if interactive && config.co_authors.unwrap_or(false) {
+        for co_author in prompts::co_authors_prompt(recent_authors(directory)) {
+            footers.lines.push(format!("Co-authored-by: {}", co_author));
+        }
+    }
+
+    if config.signed_off_by.unwrap_or(false) {
+        if let Some(author) = current_author(directory) {
+            footers.lines.push(format!("Signed-off-by: {}", author));
+        }
+    }
+    info!("Commit footers: {:?}", footers.lines);
+    footers
+}
+
+pub fn apply_footers(
+    commit_message: &str,
+    additional_commit_message: &mut Vec<String>,
+    footers: &CommitFooters,
+) -> String {
+    let mut header = commit_message.to_owned();
+    if footers.breaking {
+        if let Some(mut parsed_header) = CommitHeader::parse(commit_message) {
+            parsed_header.breaking = true;
+            header = parsed_header.format();
+        }
+    }
+    if !footers.lines.is_empty() {
+        additional_commit_message.push(footers.lines.join("\n"));
+    }
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_full_header() {
+        let header = CommitHeader::parse("feat(web)!: drop the legacy login [TEAM] #123").unwrap();
+        assert_eq!(header.commit_type, "feat");
+        assert_eq!(header.scope, Some("web".to_owned()));
+        assert!(header.breaking);
+        assert_eq!(header.subject, "drop the legacy login");
+        assert_eq!(header.team_prefix, Some("TEAM".to_owned()));
+        assert_eq!(header.issue_id, Some("123".to_owned()));
+        assert_eq!(
+            header.format(),
+            "feat(web)!: drop the legacy login [TEAM] #123"
+        );
+    }
+
+    #[test]
+    fn parse_minimal_header() {
+        let header = CommitHeader::parse("fix: handle empty diffs\n\nbody").unwrap();
+        assert_eq!(header.commit_type, "fix");
+        assert_eq!(header.scope, None);
+        assert!(!header.breaking);
+        assert_eq!(header.subject, "handle empty diffs");
+        assert_eq!(header.team_prefix, None);
+        assert_eq!(header.issue_id, None);
+    }
+
+    #[test]
+    fn parse_non_conventional_subject() {
+        assert_eq!(CommitHeader::parse("Update README"), None);
+        assert_eq!(CommitHeader::parse("Merge branch 'main'"), None);
+        assert_eq!(CommitHeader::parse(""), None);
+    }
+}
@@ -1,12 +1,29 @@
 pub mod ticket;
 extern crate clap;
 pub mod branch_utils;
+pub mod changelog;
+pub mod codeowners;
 pub mod commit;
+pub mod commit_message;
 pub mod gh;
 pub mod matches;
+pub mod milestones;
 pub mod path_utils;
+pub mod pr_body;
+pub mod pr_metadata;
+pub mod pr_title;
 pub mod progress;
+pub mod progress_clickup;
+pub mod progress_publish;
+pub mod progress_report;
+pub mod prs;
+pub mod prompt_templates;
+pub mod release;
+pub mod scopes;
+pub mod split_commit;
+pub mod stack;
 pub mod storage;
+pub mod text_merge;
 pub mod types;
 pub mod utils;
 pub mod ux_utils;
@@ -44,7 +61,7 @@ async fn main() {
     let has_gh = validate_gh();
 
     if let Some(_) = matches.subcommand_matches("push") {
-        validate_branch(&git_branch);
+        validate_branch(directory, &git_branch);
         let branch_config = get_branch_config(&git_branch, &directory).expect("Should load config");
         let branch_config_parts = branch_config.unwrap();
         branch_utils::push_pr(
@@ -61,7 +78,7 @@ async fn main() {
     }
 
     if let Some(_) = matches.subcommand_matches("commit") {
-        validate_branch(&git_branch);
+        validate_branch(directory, &git_branch);
         commit::commit(
             matches.subcommand_matches("commit").unwrap().clone(),
             &git_branch,
@@ -85,7 +102,38 @@ async fn main() {
         .await;
     }
 
-    if let Some(_) = matches.subcommand_matches("progress") {
-        progress::progress(matches.subcommand_matches("progress").unwrap().clone()).await;
+    if let Some(prompts_matches) = matches.subcommand_matches("prompts") {
+        prompt_templates::prompt_templates(prompts_matches.clone(), directory);
+    }
+
+    if let Some(changelog_matches) = matches.subcommand_matches("changelog") {
+        changelog::changelog(changelog_matches.clone(), directory, &git_branch);
+    }
+
+    if let Some(release_matches) = matches.subcommand_matches("release") {
+        release::release(
+            release_matches.clone(),
+            directory,
+            ci_mode,
+            no_verify,
+            github_api_token,
+        )
+        .await;
+    }
+
+    if let Some(milestones_matches) = matches.subcommand_matches("milestones") {
+        milestones::milestones(milestones_matches.clone(), directory, github_api_token).await;
+    }
+
+    if let Some(prs_matches) = matches.subcommand_matches("prs") {
+        prs::prs(prs_matches.clone(), directory, github_api_token, has_gh).await;
+    }
+
+    if let Some(stack_matches) = matches.subcommand_matches("stack") {
+        stack::stack(stack_matches.clone(), directory, &git_branch);
+    }
+
+    if let Some(progress_matches) = matches.subcommand_matches("progress") {
+        progress::progress(progress_matches.clone(), directory).await;
     }
 }
@@ -1,5 +1,7 @@
 use clap::{App, Arg, ArgMatches, SubCommand};
 
+use crate::{changelog, milestones, progress, progress_publish, progress_report, prs, release};
+
 pub fn build_matches() -> ArgMatches<'static> {
     App::new("Commit Message Builder")
         .version("1.0")
@@ -123,6 +125,14 @@ This data is used to run searches against the services."),
                         .help("Issue prefix")
                         .takes_value(true),
                 )
+                .arg(
+                    Arg::with_name("breaking")
+                        .short("b")
+                        .long("breaking")
+                        .value_name("description")
+                        .help("Mark the commit as a breaking change, with the given description")
+                        .takes_value(true),
+                )
                 .arg(
                     Arg::with_name("show_pr_template")
                         .short("r")
@@ -132,6 +142,175 @@ This data is used to run searches against the services."),
                         .takes_value(false),
 
                 ),
+            SubCommand::with_name("prompts")
+                .about("List, show and edit the prompt templates sent to Claude and the PR body template")
+                .after_help("Edited templates are stored in `.commit_message/prompts/` and take precedence over the defaults.")
+                .subcommands(vec![
+                    SubCommand::with_name("list").long_about("List the available prompt and PR body templates"),
+                    SubCommand::with_name("show").long_about("Show the prompt template currently in use").arg(
+                        Arg::with_name("name")
+                            .help("Name of the prompt template")
+                            .takes_value(true),
+                    ),
+                    SubCommand::with_name("edit").long_about("Edit a prompt template and save it as a repository override").arg(
+                        Arg::with_name("name")
+                            .help("Name of the prompt template")
+                            .takes_value(true),
+                    ),
+                ]),
+            SubCommand::with_name("changelog")
+                .about("Build the changelog from the commits in a range")
+                .after_help("Only commits following the `type(scope): message [TEAM] #ID` format are included.")
+                .arg(
+                    Arg::with_name("range")
+                        .help("Git range to read the commits from. Defaults to the last tag until HEAD")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("branch")
+                        .long("branch")
+                        .help("Only the commits of the current branch since it forked from its base branch")
+                        .conflicts_with("range")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("next_version")
+                        .long("next-version")
+                        .value_name("version")
+                        .help("Version the changes are released as. Unreleased if not set")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .possible_values(&changelog::CHANGELOG_FORMATS)
+                        .help("Changelog format. Defaults to keep-a-changelog")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("path")
+                        .help("File to update. Defaults to CHANGELOG.md")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("dry_run")
+                        .long("dry-run")
+                        .help("Print the changelog section instead of updating the file")
+                        .takes_value(false),
+                ),
+            SubCommand::with_name("release")
+                .about("Bump the version from the commits since the last tag, update the changelog and tag the release")
+                .after_help("Breaking changes bump the major version, features the minor version and other changes the patch version.")
+                .arg(
+                    Arg::with_name("bump")
+                        .long("bump")
+                        .possible_values(&release::BUMP_TYPES)
+                        .help("Override the detected version bump")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("github_release")
+                        .long("github-release")
+                        .help("Create a Github release with the changelog after pushing the tag")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("dry_run")
+                        .long("dry-run")
+                        .help("Show the next version and release notes without changing anything")
+                        .takes_value(false),
+                ),
+            SubCommand::with_name("milestones")
+                .about("Show the completion, due date and projected completion of the repository milestones")
+                .after_help("The projection uses the issues closed in each milestone during the rate window. Milestones projected to finish after their due date are flagged as at risk.")
+                .arg(
+                    Arg::with_name("repo")
+                        .long("repo")
+                        .help("Repository as owner/name. Defaults to the origin remote")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("state")
+                        .long("state")
+                        .possible_values(&milestones::MILESTONE_STATES)
+                        .default_value("open")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("rate_window_days")
+                        .long("rate-window-days")
+                        .help("Number of days used to compute the close rate. Defaults to 14")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("at_risk")
+                        .long("at-risk")
+                        .help("Only show the milestones at risk")
+                        .takes_value(false),
+                ),
+            SubCommand::with_name("prs")
+                .about("List your open PRs across the cached repositories with their checks, reviews and mergeability")
+                .after_help("Repositories come from the cache built by `ticket spaces`. PRs with failing checks, requested changes, unresolved threads or conflicts need attention.")
+                .arg(
+                    Arg::with_name("repo")
+                        .long("repo")
+                        .help("Only search these repositories, as owner/name or name")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("filter")
+                        .long("filter")
+                        .possible_values(&prs::PRS_FILTERS)
+                        .help("Only show the PRs matching every filter")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("sort")
+                        .long("sort")
+                        .possible_values(&prs::PRS_SORTS)
+                        .help("Sort order. Defaults to attention, PRs needing attention first")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("reverse")
+                        .long("reverse")
+                        .help("Reverse the sort order")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .possible_values(&prs::PRS_FORMATS)
+                        .help("Output format. Defaults to text")
+                        .takes_value(true),
+                ),
+            SubCommand::with_name("stack")
+                .about("Work with stacked branches, each one with a PR based on the branch below it")
+                .after_help("Without a subcommand the stack of the current branch is shown.")
+                .subcommands(vec![
+                    SubCommand::with_name("branch").long_about("Create a branch on top of the current one").arg(
+                        Arg::with_name("name")
+                            .help("Name of the new branch")
+                            .required(true)
+                            .takes_value(true),
+                    ),
+                    SubCommand::with_name("rebase").long_about("Rebase every branch of the stack on its parent, moving the branches of merged PRs down the stack").arg(
+                        Arg::with_name("push")
+                            .long("push")
+                            .help("Force push the rebased branches with lease and update their PRs")
+                            .takes_value(false),
+                    ),
+                    SubCommand::with_name("sync").long_about("Update the stack table in the body of every PR of the stack"),
+                ]),
             SubCommand::with_name("progress")
                 .after_help("Will require setting the pager to cat: `gh config set pager cat`")
                 .arg(
@@ -142,10 +321,126 @@ This data is used to run searches against the services."),
                         .takes_value(true)
                         .default_value("1,2,3"),
                 )
+                .arg(
+                    Arg::with_name("source")
+                        .long("source")
+                        .possible_values(&progress::PROGRESS_SOURCES)
+                        .help("Where the items come from. Defaults to github projects")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("clickup_api_key")
+                        .long("clickup-api-key")
+                        .env("CLICKUP_API_KEY")
+                        .help("Clickup API key, required with `--source clickup`")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("clickup_workspace_id")
+                        .long("clickup-workspace-id")
+                        .env("CLICKUP_WORKSPACE_ID")
+                        .help("Clickup workspace to read the tasks from, required with `--source clickup`")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("owner")
+                        .long("owner")
+                        .env("PULL_REQUESTS_PROGRESS_OWNER")
+                        .help("Organization or user owning the projects")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("window_end")
+                        .long("window-end")
+                        .value_name("weekday")
+                        .help("Weekday the report window ends on, in the current week. Defaults to thursday")
+                        .possible_values(&progress::WEEKDAYS)
+                        .case_insensitive(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("window_days")
+                        .long("window-days")
+                        .value_name("days")
+                        .help("Length of the report window in days. Defaults to 7")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("since")
+                        .long("since")
+                        .value_name("YYYY-MM-DD")
+                        .help("Start of the report window")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("until")
+                        .long("until")
+                        .value_name("YYYY-MM-DD")
+                        .help("End of the report window")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .possible_values(&progress_report::REPORT_FORMATS)
+                        .help("Output format for the report. Defaults to markdown")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("post")
+                        .long("post")
+                        .value_name("destination")
+                        .possible_values(&progress_publish::DESTINATIONS)
+                        .use_delimiter(true)
+                        .multiple(true)
+                        .help("Send the report to Slack, a webhook and/or a Github issue or discussion comment")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("slack_webhook_url")
+                        .long("slack-webhook-url")
+                        .env("SLACK_WEBHOOK_URL")
+                        .help("Slack incoming webhook used by `--post slack`")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("webhook_url")
+                        .long("webhook-url")
+                        .env("PULL_REQUESTS_PROGRESS_WEBHOOK_URL")
+                        .help("URL receiving the report as JSON with `--post webhook`")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("github_comment")
+                        .long("github-comment")
+                        .value_name("issue")
+                        .help("Issue or PR number or URL, or discussion URL, to comment on with `--post github`")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("dry_run")
+                        .long("dry-run")
+                        .help("Print the payloads instead of posting the report")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("group_by")
+                        .long("group-by")
+                        .possible_values(&progress::GROUP_BY_OPTIONS)
+                        .help("Group the items in each section, with subtotals. Grouping by milestone adds a burndown per milestone")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("no_snapshot")
+                        .long("no-snapshot")
+                        .help("Don't save this report as the snapshot compared against by later reports")
+                        .takes_value(false),
+                )
                 .arg(
                     Arg::with_name("slack_formatting")
                         .long("slack-formatting")
-                        .help("Format progress output for slack")
+                        .help("Format progress output for slack. Same as `--format slack`")
                 )
         ])
         .get_matches()
@@ -0,0 +1,238 @@
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use clap::ArgMatches;
+use log::{debug, info};
+use reqwest::Client;
+use std::io::{self, Write};
+
+use crate::{
+    branch_utils,
+    progress_report::format_number,
+    types::github_types::GithubMilestone,
+    utils::extract_github_spaces_data::make_github_request,
+    ux_utils::exit_with_error,
+};
+
+pub const MILESTONE_STATES: [&str; 3] = ["open", "closed", "all"];
+const DEFAULT_RATE_WINDOW_DAYS: i64 = 14;
+const PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct MilestoneHealth {
+    pub title: String,
+    pub url: String,
+    pub open: u32,
+    pub closed: u32,
+    pub due_on: Option<NaiveDate>,
+    pub days_left: Option<i64>,
+    // Issues closed per week over the rate window
+    pub close_rate: f64,
+    pub projected_on: Option<NaiveDate>,
+    pub at_risk: bool,
+}
+
+impl MilestoneHealth {
+    pub fn percentage(&self) -> u32 {
+        match self.open + self.closed {
+            0 => 0,
+            total => self.closed * 100 / total,
+        }
+    }
+
+    pub fn line(&self) -> String {
+        let percentage = self.percentage() as usize;
+        let filled = percentage / 10;
+        let mut line = format!(
+            "\x1b[1;1m{}\x1b[0m {}{} {}% ({}/{} closed)",
+            self.title,
+            "▓".repeat(filled),
+            "░".repeat(10 - filled),
+            percentage,
+            self.closed,
+            self.open + self.closed
+        );
+        match (self.due_on, self.days_left) {
+            (Some(due_on), Some(d)) if d < 0 => {
+                line.push_str(&format!(" · due {}, {} days overdue", due_on, -d))
+            }
+            (Some(due_on), Some(d)) => line.push_str(&format!(" · due {}, {} days left", due_on, d)),
+            _ => line.push_str(" · no due date"),
+        }
+        line.push_str(&format!(
+            " · {} closed per week",
+            format_number((self.close_rate * 10.0).round() / 10.0)
+        ));
+        match (self.open, self.projected_on) {
+            (0, _) => {}
+            (_, Some(projected_on)) => line.push_str(&format!(", projected {}", projected_on)),
+            (_, None) => line.push_str(", no projection"),
+        }
+        if self.at_risk {
+            line.push_str(" \x1b[1;31m⚠️ at risk\x1b[0m");
+        }
+        line
+    }
+}
+
+fn parse_github_date(date: &str) -> Option<NaiveDate> {
+    DateTime::parse_from_rfc3339(date)
+        .ok()
+        .map(|d| d.date_naive())
+        .or(NaiveDate::parse_from_str(date.get(..10).unwrap_or(date), "%Y-%m-%d").ok())
+}
+
+// Projects the completion date from the recent close rate. Milestones are at risk when
+// the projection lands after the due date, or nothing is being closed before it
+pub fn milestone_health(
+    milestone: &GithubMilestone,
+    recently_closed: usize,
+    rate_window_days: i64,
+    today: NaiveDate,
+) -> MilestoneHealth {
+    let open = milestone.open_issues.unwrap_or(0);
+    let closed = milestone.closed_issues.unwrap_or(0);
+    let due_on = milestone.due_on.as_deref().and_then(parse_github_date);
+    let days_left = due_on.map(|d| (d - today).num_days());
+    let per_day = recently_closed as f64 / rate_window_days.max(1) as f64;
+    let projected_on = match (open, per_day > 0.0) {
+        (0, _) => Some(today),
+        (_, true) => Some(today + Duration::days((open as f64 / per_day).ceil() as i64)),
+        (_, false) => None,
+    };
+    let at_risk = match (open, due_on, projected_on) {
+        (0, _, _) => false,
+        (_, Some(due_on), Some(projected_on)) => projected_on > due_on,
+        (_, Some(_), None) => true,
+        (_, None, _) => false,
+    };
+    MilestoneHealth {
+        title: milestone.title.clone().unwrap_or_default(),
+        url: milestone.html_url.clone().unwrap_or_default(),
+        open,
+        closed,
+        due_on,
+        days_left,
+        close_rate: per_day * 7.0,
+        projected_on,
+        at_risk,
+    }
+}
+
+async fn fetch_milestones(
+    client: &Client,
+    repo: &str,
+    state: &str,
+    github_api_token: Option<&str>,
+) -> Result<Vec<GithubMilestone>, Box<dyn std::error::Error>> {
+    let mut milestones: Vec<GithubMilestone> = vec![];
+    let mut page = 1;
+    loop {
+        let url = format!(
+            "https://api.github.com/repos/{}/milestones?state={}&sort=due_on&per_page={}&page={}",
+            repo, state, PAGE_SIZE, page
+        );
+        debug!("Fetching {}", url);
+        let body = make_github_request(client, &url, github_api_token).await?;
+        let page_milestones: Vec<GithubMilestone> = serde_json::from_value(body)?;
+        let page_size = page_milestones.len();
+        milestones.extend(page_milestones);
+        if page_size < PAGE_SIZE {
+            break;
+        }
+        page += 1;
+    }
+    Ok(milestones)
+}
+
+// Issues and pull requests of the milestone closed on or after `since`
+async fn count_closed_since(
+    client: &Client,
+    repo: &str,
+    milestone_number: u32,
+    since: NaiveDate,
+    github_api_token: Option<&str>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut count = 0;
+    let mut page = 1;
+    loop {
+        // `since` filters on the last update, so the close date is checked below
+        let url = format!(
+            "https://api.github.com/repos/{}/issues?milestone={}&state=closed&since={}T00:00:00Z&per_page={}&page={}",
+            repo, milestone_number, since, PAGE_SIZE, page
+        );
+        debug!("Fetching {}", url);
+        let body = make_github_request(client, &url, github_api_token).await?;
+        let issues = body.as_array().ok_or("Issues response is not an array")?;
+        count += issues
+            .iter()
+            .filter_map(|i| i.get("closed_at").and_then(|c| c.as_str()))
+            .filter_map(parse_github_date)
+            .filter(|closed_at| *closed_at >= since)
+            .count();
+        if issues.len() < PAGE_SIZE {
+            break;
+        }
+        page += 1;
+    }
+    Ok(count)
+}
+
+pub async fn milestones(matches: ArgMatches<'_>, directory: &str, github_api_token: Option<&str>) {
+    if github_api_token.is_none() {
+        exit_with_error("A Github API token is required to list milestones.");
+    }
+    let repo = match matches.value_of("repo") {
+        Some(x) => x.to_owned(),
+        None => match branch_utils::get_branch_origin_parts(directory) {
+            Ok(parts) => match parts.owner_and_path {
+                Some(x) => x,
+                None => exit_with_error("The origin remote is not a Github repository."),
+            },
+            Err(e) => exit_with_error(&format!("Could not read the origin remote: {}", e)),
+        },
+    };
+    let state = matches.value_of("state").unwrap_or("open");
+    let rate_window_days = matches
+        .value_of("rate_window_days")
+        .map(|d| match d.parse::<i64>() {
+            Ok(x) if x > 0 => x,
+            _ => exit_with_error(&format!("Invalid number of days {}", d)),
+        })
+        .unwrap_or(DEFAULT_RATE_WINDOW_DAYS);
+    let at_risk_only = matches.is_present("at_risk");
+
+    let client = Client::new();
+    let milestones = match fetch_milestones(&client, &repo, state, github_api_token).await {
+        Ok(x) => x,
+        Err(e) => exit_with_error(&format!("Could not fetch the milestones of {}: {}", repo, e)),
+    };
+    info!("Found {} milestones in {}", milestones.len(), repo);
+
+    let today = Local::now().date_naive();
+    let since = today - Duration::days(rate_window_days);
+    let mut health: Vec<MilestoneHealth> = vec![];
+    for milestone in &milestones {
+        let Some(number) = milestone.number else {
+            continue;
+        };
+        let recently_closed =
+            match count_closed_since(&client, &repo, number, since, github_api_token).await {
+                Ok(x) => x,
+                Err(e) => exit_with_error(&format!(
+                    "Could not fetch the issues of milestone {}: {}",
+                    number, e
+                )),
+            };
+        health.push(milestone_health(milestone, recently_closed, rate_window_days, today));
+    }
+
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    let shown: Vec<&MilestoneHealth> = health.iter().filter(|h| !at_risk_only || h.at_risk).collect();
+    if shown.is_empty() {
+        writeln!(handle, "No milestones found in {}", repo).unwrap_or_default();
+    }
+    for milestone in shown {
+        writeln!(handle, "{}\n  {}", milestone.line(), milestone.url).unwrap_or_default();
+    }
+    let _ = handle.flush();
+}
@@ -0,0 +1,458 @@
+use lazy_static::lazy_static;
+use log::{debug, info};
+use regex::Regex;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use crate::{branch_utils, prompt_templates, types::github_types::GithubPullRequest};
+
+pub const RISK_LEVELS: [&str; 4] = ["High", "Medium", "Low", "Trivial"];
+
+// Where Github looks for a single PR template, in order
+pub const REPO_TEMPLATE_PATHS: [&str; 4] = [
+    ".github/pull_request_template.md",
+    ".github/PULL_REQUEST_TEMPLATE.md",
+    "PULL_REQUEST_TEMPLATE.md",
+    "docs/pull_request_template.md",
+];
+
+const DESCRIPTION_SECTION: &str = "description";
+const RISK_SECTION: &str = "risk";
+const TEST_STEPS_SECTION: &str = "test_steps";
+const CHECKLIST_SECTION: &str = "checklist";
+const ISSUE_SECTION: &str = "issue";
+
+// Heading keywords for each section, checked in order so "How to test" isn't a description
+const SECTION_KEYWORDS: [(&str, &[&str]); 5] = [
+    (RISK_SECTION, &["risk", "impact"]),
+    (TEST_STEPS_SECTION, &["test", "qa", "verify", "verification"]),
+    (CHECKLIST_SECTION, &["checklist", "check list"]),
+    (ISSUE_SECTION, &["issue", "ticket", "related", "fixes", "closes", "linked"]),
+    (
+        DESCRIPTION_SECTION,
+        &["description", "summary", "what", "why", "changes", "overview", "context"],
+    ),
+];
+
+// Fields requested from `gh pr view` to evaluate the checklist
+pub const PR_STATE_FIELDS: &str = "number,title,body,isDraft,labels,assignees,autoMergeRequest,files";
+
+const DESCRIPTION_ITEM: &str = "description";
+const ISSUE_ITEM: &str = "issue";
+const RISK_ITEM: &str = "risk";
+const TESTS_ITEM: &str = "tests";
+const LABELS_ITEM: &str = "labels";
+const ASSIGNEES_ITEM: &str = "assignees";
+const READY_ITEM: &str = "ready";
+const AUTO_MERGE_ITEM: &str = "auto_merge";
+
+// Checklist item keywords, checked in order so "risk level, how to test" is about the risk
+const CHECKLIST_KEYWORDS: [(&str, &[&str]); 8] = [
+    (RISK_ITEM, &["risk"]),
+    (AUTO_MERGE_ITEM, &["auto merge", "auto-merge", "automerge"]),
+    (TESTS_ITEM, &["test"]),
+    (LABELS_ITEM, &["label"]),
+    (ASSIGNEES_ITEM, &["assign"]),
+    (READY_ITEM, &["ready", "draft"]),
+    (ISSUE_ITEM, &["issue", "linked"]),
+    (DESCRIPTION_ITEM, &["description"]),
+];
+
+const PLACEHOLDERS: [&str; 3] = ["...", "Because...", "1."];
+
+lazy_static! {
+    static ref CHECKBOX_REGEX: Regex =
+        Regex::new(r"^(?P<prefix>\s*[-*] \[)(?P<mark>[ xX])(?P<suffix>\] )(?P<text>.*)$").unwrap();
+    static ref ISSUE_REFERENCE_REGEX: Regex =
+        Regex::new(r"(?i)\b(fix(es|ed)?|close[sd]?|resolve[sd]?) #\S+").unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChecklistItem {
+    pub checked: bool,
+    pub text: String,
+}
+
+impl ChecklistItem {
+    pub fn new(checked: bool, text: &str) -> ChecklistItem {
+        ChecklistItem {
+            checked,
+            text: text.to_owned(),
+        }
+    }
+
+    pub fn line(&self) -> String {
+        let mark = match self.checked {
+            true => "x",
+            false => " ",
+        };
+        format!("- [{}] {}", mark, self.text)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PrBody {
+    pub description: Option<String>,
+    // One of RISK_LEVELS
+    pub risk_level: Option<String>,
+    pub risk_description: Option<String>,
+    pub test_steps: Option<String>,
+    pub issue_id: String,
+}
+
+impl PrBody {
+    pub fn risk_label(&self) -> Option<&'static str> {
+        match self.risk_level.as_deref() {
+            Some("High") => Some("🚨HIGH🚨"),
+            Some("Medium") => Some("⚠️MEDIUM⚠️"),
+            Some("Low") => Some("👍LOW👍"),
+            Some("Trivial") => Some("✅TRIVIAL✅"),
+            _ => None,
+        }
+    }
+
+    pub fn issue_reference(&self) -> Option<String> {
+        match self.issue_id.is_empty() {
+            true => None,
+            false => Some(format!("fixes #{}", self.issue_id)),
+        }
+    }
+
+    pub fn checklist(&self) -> Vec<ChecklistItem> {
+        let mut issue_item = "🔗 I linked this PR to an issue (which is in progress)".to_owned();
+        if let Some(reference) = self.issue_reference() {
+            issue_item.push_str(&format!(": {}", reference));
+        }
+        vec![
+            ChecklistItem::new(
+                self.description.is_some(),
+                "✍️ I wrote an easy-to-read, short description at the top, with a good title",
+            ),
+            ChecklistItem::new(!self.issue_id.is_empty(), &issue_item),
+            ChecklistItem::new(
+                false,
+                "📋 I filled out the risk level, how to test, impact, what the PR does",
+            ),
+            ChecklistItem::new(false, "🧪 I added or updated tests"),
+            ChecklistItem::new(false, "🏷️ I added the right labels"),
+            ChecklistItem::new(false, "🥸 I assigned myself to the PR and others (as needed)"),
+            ChecklistItem::new(
+                false,
+                "🚀 I moved the PR into ready state - it's ready to be reviewed!",
+            ),
+            ChecklistItem::new(false, "🤖 I enabled auto merge"),
+        ]
+    }
+
+    // Content for a section of a repository template, None leaves the section as it is
+    fn section_content(&self, section: &str) -> Option<String> {
+        match section {
+            DESCRIPTION_SECTION => self.description.clone(),
+            RISK_SECTION => match (self.risk_label(), &self.risk_description) {
+                (None, None) => None,
+                (label, description) => Some(
+                    vec![
+                        label.map(|l| format!("🚦 This is a {} risk PR", l)),
+                        description.clone(),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<String>>()
+                    .join("\n\n"),
+                ),
+            },
+            TEST_STEPS_SECTION => self.test_steps.clone(),
+            ISSUE_SECTION => self.issue_reference(),
+            CHECKLIST_SECTION => Some(
+                self.checklist()
+                    .iter()
+                    .map(|i| i.line())
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            ),
+            _ => None,
+        }
+    }
+
+    fn template_variables(&self) -> HashMap<&'static str, String> {
+        HashMap::from([
+            (
+                "description",
+                self.description.clone().unwrap_or("...".to_owned()),
+            ),
+            ("risk_level", self.risk_label().unwrap_or("").to_owned()),
+            // Only shown when a risk level was chosen
+            (
+                "risk_heading",
+                self.risk_label()
+                    .map(|l| format!("# 🚦 This is a {} risk PR\n\n", l))
+                    .unwrap_or_default(),
+            ),
+            (
+                "risk_description",
+                self.risk_description.clone().unwrap_or("Because...".to_owned()),
+            ),
+            ("test_steps", self.test_steps.clone().unwrap_or("1.".to_owned())),
+            ("issue_id", self.issue_id.clone()),
+            (
+                "checklist",
+                self.section_content(CHECKLIST_SECTION).unwrap_or_default(),
+            ),
+        ])
+    }
+}
+
+pub fn repo_template(directory: &str) -> Option<(String, String)> {
+    REPO_TEMPLATE_PATHS.iter().find_map(|path| {
+        fs::read_to_string(Path::new(directory).join(path))
+            .ok()
+            .map(|body| (path.to_string(), body))
+    })
+}
+
+fn heading_text(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    match (level, trimmed[level..].starts_with(' ')) {
+        (1..=6, true) => Some(trimmed[level..].trim()),
+        _ => None,
+    }
+}
+
+pub fn heading_section(heading: &str) -> Option<&'static str> {
+    let heading = heading.to_lowercase();
+    SECTION_KEYWORDS
+        .iter()
+        .find(|(_, keywords)| keywords.iter().any(|k| heading.contains(k)))
+        .map(|(section, _)| *section)
+}
+
+fn has_checklist(lines: &[&str]) -> bool {
+    lines.iter().any(|l| {
+        let l = l.trim_start();
+        l.starts_with("- [ ]") || l.starts_with("- [x]") || l.starts_with("* [ ]")
+    })
+}
+
+// Keeps the template comments, they are hidden in the rendered PR but guide whoever edits it
+fn fill_section(lines: &[&str], content: &str) -> Vec<String> {
+    let mut filled: Vec<String> = vec![];
+    let mut in_comment = false;
+    for line in lines {
+        let is_comment_line = in_comment || line.trim_start().starts_with("<!--");
+        if line.contains("<!--") {
+            in_comment = true;
+        }
+        if line.contains("-->") {
+            in_comment = false;
+        }
+        if is_comment_line {
+            filled.push(line.to_string());
+        }
+    }
+    filled.push(content.to_owned());
+    filled.push("".to_owned());
+    filled
+}
+
+// Fills the headings we know about and leaves everything else in the template intact
+pub fn fill_repo_template(template: &str, body: &PrBody) -> String {
+    let lines: Vec<&str> = template.lines().collect();
+    let mut output: Vec<String> = vec![];
+    let mut filled_sections: Vec<&str> = vec![];
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        index += 1;
+        output.push(line.to_owned());
+        let Some(section) = heading_text(line).and_then(heading_section) else {
+            continue;
+        };
+        let section_end = lines[index..]
+            .iter()
+            .position(|l| heading_text(l).is_some())
+            .map(|p| index + p)
+            .unwrap_or(lines.len());
+        let section_lines = &lines[index..section_end];
+        // Repository checklists are the team's own, so they are kept as they are
+        let keep = (section == CHECKLIST_SECTION && has_checklist(section_lines))
+            || filled_sections.contains(&section);
+        match (keep, body.section_content(section)) {
+            (false, Some(content)) => {
+                debug!("Filling the {} section under {}", section, line);
+                output.extend(fill_section(section_lines, &content));
+                filled_sections.push(section);
+            }
+            _ => output.extend(section_lines.iter().map(|l| l.to_string())),
+        }
+        index = section_end;
+    }
+
+    // Github only links the issue when the reference is in the body
+    if !filled_sections.contains(&ISSUE_SECTION) {
+        if let Some(reference) = body.issue_reference() {
+            output.push("".to_owned());
+            output.push(reference);
+        }
+    }
+    if !filled_sections.contains(&DESCRIPTION_SECTION) {
+        if let Some(description) = &body.description {
+            output.insert(0, "".to_owned());
+            output.insert(0, description.clone());
+        }
+    }
+    output.join("\n").trim_end().to_owned() + "\n"
+}
+
+pub fn render(directory: &str, body: &PrBody) -> String {
+    let rendered = match repo_template(directory) {
+        Some((path, template)) => {
+            info!("Using the repository PR template {}", path);
+            fill_repo_template(&template, body)
+        }
+        None => prompt_templates::render_prompt(
+            directory,
+            prompt_templates::PR_BODY_TEMPLATE,
+            &body.template_variables(),
+        ),
+    };
+    info!("PR template:\n{}", rendered);
+    rendered
+}
+
+fn is_test_file(path: &str) -> bool {
+    branch_utils::classify_file(path) == "test"
+        || path
+            .split('/')
+            .any(|segment| ["test", "tests", "__tests__", "spec"].contains(&segment))
+        || path.ends_with("_test.rs")
+        || path.ends_with("_test.go")
+        || path.ends_with("_test.py")
+}
+
+fn is_filled(lines: &[&str]) -> bool {
+    lines
+        .iter()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with("<!--") && !l.ends_with("-->"))
+        .any(|l| !PLACEHOLDERS.contains(&l))
+}
+
+// Whether any heading for the section, or the text before the first heading for the
+// description, has something other than comments and placeholders
+fn section_filled(body: &str, section: &str) -> bool {
+    let lines: Vec<&str> = body.lines().collect();
+    let headings: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| heading_text(l).is_some())
+        .map(|(i, _)| i)
+        .collect();
+    let first_heading = headings.first().copied().unwrap_or(lines.len());
+    if section == DESCRIPTION_SECTION && is_filled(&lines[..first_heading]) {
+        return true;
+    }
+    headings.iter().enumerate().any(|(position, start)| {
+        let end = headings.get(position + 1).copied().unwrap_or(lines.len());
+        heading_text(lines[*start]).and_then(heading_section) == Some(section)
+            && is_filled(&lines[start + 1..end])
+    })
+}
+
+pub fn checklist_item_key(text: &str) -> Option<&'static str> {
+    let text = text.to_lowercase();
+    CHECKLIST_KEYWORDS
+        .iter()
+        .find(|(_, keywords)| keywords.iter().any(|k| text.contains(k)))
+        .map(|(key, _)| *key)
+}
+
+// Some(state) for the facts Github reports, and only Some(true) for the ones read from the
+// body or the files, since missing them doesn't mean the author didn't do it
+fn checklist_fact(key: &str, body: &str, pr: &GithubPullRequest) -> Option<bool> {
+    let observed = |fact: bool| match fact {
+        true => Some(true),
+        false => None,
+    };
+    match key {
+        LABELS_ITEM => pr.labels.as_ref().map(|l| !l.is_empty()),
+        ASSIGNEES_ITEM => pr.assignees.as_ref().map(|a| !a.is_empty()),
+        READY_ITEM => pr.is_draft.map(|d| !d),
+        AUTO_MERGE_ITEM => pr.number.map(|_| pr.auto_merge_request.is_some()),
+        DESCRIPTION_ITEM => observed(section_filled(body, DESCRIPTION_SECTION)),
+        ISSUE_ITEM => observed(ISSUE_REFERENCE_REGEX.is_match(body)),
+        RISK_ITEM => observed(
+            section_filled(body, RISK_SECTION) && section_filled(body, TEST_STEPS_SECTION),
+        ),
+        TESTS_ITEM => observed(
+            pr.files
+                .iter()
+                .flatten()
+                .filter_map(|f| f.get("path").and_then(|p| p.as_str()))
+                .any(is_test_file),
+        ),
+        _ => None,
+    }
+}
+
+pub fn ticked_items(body: &str) -> HashSet<String> {
+    body.lines()
+        .filter_map(|l| CHECKBOX_REGEX.captures(l))
+        .filter(|c| &c["mark"] != " ")
+        .map(|c| c["text"].trim().to_owned())
+        .collect()
+}
+
+// Ticks the items in `ticked` and unticks every other one
+pub fn set_checkboxes(body: &str, ticked: &HashSet<String>) -> String {
+    let mut lines: Vec<String> = vec![];
+    for line in body.lines() {
+        let line = match CHECKBOX_REGEX.captures(line) {
+            Some(caps) => {
+                let mark = match ticked.contains(caps["text"].trim()) {
+                    true => "x",
+                    false => " ",
+                };
+                format!("{}{}{}{}", &caps["prefix"], mark, &caps["suffix"], &caps["text"])
+            }
+            None => line.to_owned(),
+        };
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+// Only the checkbox marks change. Items without a fact keep their mark, or the one
+// they have in the PR on Github when it was ticked there
+pub fn tick_checklist(body: &str, pr: &GithubPullRequest) -> String {
+    let ticked_on_github = ticked_items(pr.body.as_deref().unwrap_or(""));
+    let mut lines: Vec<String> = vec![];
+    for line in body.lines() {
+        let Some(caps) = CHECKBOX_REGEX.captures(line) else {
+            lines.push(line.to_owned());
+            continue;
+        };
+        let text = caps["text"].trim();
+        let checked = match checklist_item_key(text).and_then(|k| checklist_fact(k, body, pr)) {
+            Some(fact) => fact,
+            None => &caps["mark"] != " " || ticked_on_github.contains(text),
+        };
+        let mark = match checked {
+            true => "x",
+            false => " ",
+        };
+        debug!("Checklist item {:?} is {}", text, mark);
+        lines.push(format!(
+            "{}{}{}{}",
+            &caps["prefix"], mark, &caps["suffix"], &caps["text"]
+        ));
+    }
+    let mut ticked = lines.join("\n");
+    if body.ends_with('\n') {
+        ticked.push('\n');
+    }
+    ticked
+}
@@ -0,0 +1,234 @@
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    process::Command,
+};
+
+use crate::{branch_utils, commit_message::CommitHeader, storage};
+
+pub const MERGE_STRATEGIES: [&str; 3] = ["squash", "merge", "rebase"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PullRequestConfig {
+    // Added to every PR
+    pub labels: Option<Vec<String>>,
+    // Commit type -> label, e.g. `fix: bug`
+    pub type_labels: Option<BTreeMap<String, String>>,
+    // Users or org/team slugs
+    pub reviewers: Option<Vec<String>>,
+    // Code owners of the branch files: `suggest` (default), `request` or `off`
+    pub codeowners_reviewers: Option<String>,
+    // Defaults to `@me`
+    pub assignees: Option<Vec<String>>,
+    pub draft: Option<bool>,
+    // Use the milestone of the Github issue in the branch name, defaults to true
+    pub milestone_from_issue: Option<bool>,
+    // `squash`, `merge` or `rebase`. Auto-merge is off when not set
+    pub auto_merge: Option<String>,
+    // Check the PR title follows the commit header format. Defaults to true for squash merges
+    pub validate_title: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PrMetadata {
+    pub labels: Vec<String>,
+    pub reviewers: Vec<String>,
+    pub assignees: Vec<String>,
+    pub draft: bool,
+    pub milestone: Option<String>,
+    pub auto_merge: Option<String>,
+    // Parent branch for stacked PRs, the repository default otherwise
+    pub base: Option<String>,
+}
+
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+impl PrMetadata {
+    // Flags for `gh pr create`
+    pub fn create_args(&self) -> String {
+        let mut args = String::new();
+        for (flag, values) in [
+            ("--label", &self.labels),
+            ("--reviewer", &self.reviewers),
+            ("--assignee", &self.assignees),
+        ]
+        .iter()
+        {
+            for value in values.iter() {
+                args.push_str(&format!(" {} {}", flag, shell_quote(value)));
+            }
+        }
+        if self.draft {
+            args.push_str(" --draft");
+        }
+        if let Some(milestone) = &self.milestone {
+            args.push_str(&format!(" --milestone {}", shell_quote(milestone)));
+        }
+        if let Some(base) = &self.base {
+            args.push_str(&format!(" --base {}", shell_quote(base)));
+        }
+        args
+    }
+}
+
+pub fn pull_request_config(directory: &str) -> PullRequestConfig {
+    storage::load_repo_config(directory)
+        .pull_request
+        .unwrap_or_default()
+}
+
+// Only Github issues have milestones, other trackers use non numeric ids
+pub fn issue_milestone(directory: &str, issue_id: &str) -> Option<String> {
+    if issue_id.is_empty() || !issue_id.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let cmd_arg = format!(
+        "cd {} && gh issue view {} --json milestone --jq '.milestone.title // empty'",
+        directory, issue_id
+    );
+    let output = Command::new("sh").arg("-c").arg(cmd_arg).output().ok()?;
+    if !output.status.success() {
+        debug!(
+            "Could not read the milestone of issue {}: {}",
+            issue_id,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_owned()).filter(|m| !m.is_empty())
+}
+
+// `gh pr create` fails when a label is missing, so those are left out with a warning
+fn existing_labels(directory: &str, labels: Vec<String>) -> Vec<String> {
+    if labels.is_empty() {
+        return labels;
+    }
+    let cmd_arg = format!(
+        "cd {} && gh label list -L 1000 --json name --jq '.[].name'",
+        directory
+    );
+    let repo_labels: Vec<String> = match Command::new("sh").arg("-c").arg(cmd_arg).output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|l| l.trim().to_lowercase())
+            .collect(),
+        _ => {
+            info!("Could not list the repository labels, keeping {:?}", labels);
+            return labels;
+        }
+    };
+    let (existing, missing): (Vec<String>, Vec<String>) = labels
+        .into_iter()
+        .partition(|label| repo_labels.contains(&label.to_lowercase()));
+    if !missing.is_empty() {
+        let stdout = io::stdout();
+        let mut handle = io::BufWriter::new(&stdout);
+        writeln!(
+            handle,
+            "\x1b[1;33mSkipping labels that don't exist in the repository:\x1b[0m {}",
+            missing.join(", ")
+        )
+        .unwrap_or_default();
+        let _ = handle.flush();
+    }
+    existing
+}
+
+pub fn pr_metadata(directory: &str, git_branch: &str, title: &str) -> PrMetadata {
+    let repo_config = storage::load_repo_config(directory);
+    let config = repo_config.pull_request.unwrap_or_default();
+    let branch_config = storage::get_branch_config(git_branch, directory)
+        .ok()
+        .flatten();
+
+    let mut labels = config.labels.clone().unwrap_or_default();
+    if repo_config.scope_labels.unwrap_or(false) {
+        if let Some(scopes) = branch_config.as_ref().and_then(|c| c.scopes.clone()) {
+            labels.extend(scopes);
+        }
+    }
+    if let Some(header) = CommitHeader::parse(title) {
+        if let Some(label) = config
+            .type_labels
+            .as_ref()
+            .and_then(|t| t.get(&header.commit_type))
+        {
+            labels.push(label.clone());
+        }
+    }
+    let mut unique_labels: Vec<String> = vec![];
+    for label in labels {
+        if !unique_labels.contains(&label) {
+            unique_labels.push(label);
+        }
+    }
+
+    let unique_labels = existing_labels(directory, unique_labels);
+
+    let milestone = match config.milestone_from_issue.unwrap_or(true) {
+        true => branch_config
+            .as_ref()
+            .and_then(|c| c.issue_id.clone())
+            .and_then(|issue_id| issue_milestone(directory, &issue_id)),
+        false => None,
+    };
+
+    let auto_merge = match config.auto_merge {
+        Some(strategy) if MERGE_STRATEGIES.contains(&strategy.as_str()) => Some(strategy),
+        Some(strategy) => {
+            let stdout = io::stdout();
+            let mut handle = io::BufWriter::new(&stdout);
+            writeln!(
+                handle,
+                "\x1b[1;33mUnknown auto-merge strategy {}, use one of {}\x1b[0m",
+                strategy,
+                MERGE_STRATEGIES.join(", ")
+            )
+            .unwrap_or_default();
+            let _ = handle.flush();
+            None
+        }
+        None => None,
+    };
+
+    let metadata = PrMetadata {
+        labels: unique_labels,
+        reviewers: config.reviewers.unwrap_or_default(),
+        assignees: config.assignees.unwrap_or(vec!["@me".to_owned()]),
+        draft: config.draft.unwrap_or(false),
+        milestone,
+        auto_merge,
+        base: Some(branch_utils::base_branch(directory, git_branch)),
+    };
+    info!("PR metadata: {:?}", metadata);
+    metadata
+}
+
+pub fn enable_auto_merge(directory: &str, strategy: &str) -> bool {
+    let cmd_arg = format!("cd {} && gh pr merge --auto --{}", directory, strategy);
+    info!("Executing command: {}", cmd_arg);
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    match Command::new("sh").arg("-c").arg(cmd_arg).output() {
+        Ok(output) if output.status.success() => {
+            writeln!(handle, "Auto-merge ({}) enabled", strategy).unwrap_or_default();
+            let _ = handle.flush();
+            true
+        }
+        Ok(output) => {
+            writeln!(
+                handle,
+                "\x1b[1;33mCould not enable auto-merge:\x1b[0m {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .unwrap_or_default();
+            let _ = handle.flush();
+            false
+        }
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,209 @@
+use inquire::{validator::Validation, Text};
+use log::{debug, info};
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    process::{self, Command},
+};
+
+use crate::{
+    commit_message::{CommitHeader, MAX_SUBJECT_LENGTH},
+    pr_metadata::{self, shell_quote},
+    prompts, storage,
+    utils::git::git_output,
+};
+
+fn short_types() -> Vec<&'static str> {
+    prompts::TYPE_OPTIONS
+        .iter()
+        .map(|t| t.split(':').next().unwrap_or(""))
+        .collect()
+}
+
+// The rules commit headers follow, so a squash merge produces a valid commit
+pub fn validate_title(title: &str) -> Result<CommitHeader, String> {
+    let header = CommitHeader::parse(title)
+        .ok_or("The title should look like `type(scope): subject [PREFIX] #id`".to_owned())?;
+    if !short_types().contains(&header.commit_type.as_str()) {
+        return Err(format!(
+            "Unknown type {}, use one of {}",
+            header.commit_type,
+            short_types().join(", ")
+        ));
+    }
+    let length = header.subject.chars().count();
+    if length == 0 {
+        return Err("The subject is empty".to_owned());
+    }
+    if length > MAX_SUBJECT_LENGTH {
+        return Err(format!(
+            "The subject limit is {} characters. You have {}.",
+            MAX_SUBJECT_LENGTH, length
+        ));
+    }
+    Ok(header)
+}
+
+pub fn branch_commit_headers(directory: &str, base: &str) -> Vec<String> {
+    git_output(directory, &format!("log --no-merges --format=%s {}..HEAD", base))
+        .unwrap_or_default()
+        .lines()
+        .map(|l| l.trim().to_owned())
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+// Most frequent type, ties go to the one listed first in the type options
+pub fn dominant_type(headers: &[CommitHeader]) -> Option<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for header in headers {
+        *counts.entry(header.commit_type.as_str()).or_default() += 1;
+    }
+    // `max_by_key` keeps the last of equal elements, hence the reversed order
+    short_types()
+        .into_iter()
+        .rev()
+        .filter(|t| counts.contains_key(t))
+        .max_by_key(|t| counts[t])
+        .map(|t| t.to_owned())
+}
+
+fn truncate_subject(subject: &str) -> String {
+    if subject.chars().count() <= MAX_SUBJECT_LENGTH {
+        return subject.to_owned();
+    }
+    let mut truncated = String::new();
+    for word in subject.split_whitespace() {
+        if truncated.chars().count() + word.chars().count() + 1 > MAX_SUBJECT_LENGTH {
+            break;
+        }
+        if !truncated.is_empty() {
+            truncated.push(' ');
+        }
+        truncated.push_str(word);
+    }
+    // A first word longer than the limit is cut
+    if truncated.is_empty() {
+        return subject.chars().take(MAX_SUBJECT_LENGTH).collect();
+    }
+    truncated
+}
+
+// Acronyms and names in the issue name keep their case
+fn lowercase_first(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => "".to_owned(),
+    }
+}
+
+// Single commit branches keep the commit header. With more commits the subject is the
+// issue name and the type the one most commits have
+pub fn build_title(directory: &str, git_branch: &str, commit_message: &str, base: &str) -> String {
+    let commit_headers: Vec<CommitHeader> = branch_commit_headers(directory, base)
+        .iter()
+        .filter_map(|h| CommitHeader::parse(h))
+        .collect();
+    let issue_name = storage::get_branch_config(git_branch, directory)
+        .ok()
+        .flatten()
+        .and_then(|c| c.issue_name)
+        .filter(|n| !n.trim().is_empty());
+    debug!(
+        "{} commits in the branch, issue name {:?}",
+        commit_headers.len(),
+        issue_name
+    );
+    if commit_headers.len() < 2 {
+        return commit_message.to_owned();
+    }
+    let (Some(issue_name), Some(commit_type)) = (issue_name, dominant_type(&commit_headers)) else {
+        return commit_message.to_owned();
+    };
+
+    let last_header = CommitHeader::parse(commit_message);
+    let scopes: Vec<&Option<String>> = commit_headers.iter().map(|h| &h.scope).collect();
+    let scope = match scopes.iter().all(|s| *s == scopes[0]) {
+        true => scopes[0].clone(),
+        false => None,
+    };
+    let title = CommitHeader {
+        commit_type,
+        scope,
+        breaking: commit_headers.iter().any(|h| h.breaking),
+        subject: truncate_subject(&lowercase_first(issue_name.trim())),
+        team_prefix: last_header.as_ref().and_then(|h| h.team_prefix.clone()),
+        issue_id: last_header.as_ref().and_then(|h| h.issue_id.clone()),
+    }
+    .format();
+    info!("PR title: {}", title);
+    title
+}
+
+const MERGE_SETTINGS_FIELDS: &str = "squashMergeAllowed,mergeCommitAllowed,rebaseMergeAllowed";
+const SQUASH_ONLY_QUERY: &str =
+    ".squashMergeAllowed and (.mergeCommitAllowed | not) and (.rebaseMergeAllowed | not)";
+
+// Repositories where squash is the only merge method allowed on Github
+fn squash_only_repo(directory: &str) -> bool {
+    let cmd_arg = format!(
+        "cd {} && gh repo view --json {} --jq {}",
+        shell_quote(directory),
+        MERGE_SETTINGS_FIELDS,
+        shell_quote(SQUASH_ONLY_QUERY)
+    );
+    match Command::new("sh").arg("-c").arg(cmd_arg).output() {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim() == "true"
+        }
+        _ => {
+            debug!("Could not read the merge settings of the repository");
+            false
+        }
+    }
+}
+
+// Only enforced for squash merges unless the config says otherwise, since the title is
+// the commit that ends up in the base branch
+pub fn should_validate(directory: &str) -> bool {
+    let config = pr_metadata::pull_request_config(directory);
+    config.validate_title.unwrap_or_else(|| {
+        config.auto_merge.as_deref() == Some("squash") || squash_only_repo(directory)
+    })
+}
+
+pub fn pr_title(
+    directory: &str,
+    git_branch: &str,
+    commit_message: &str,
+    base: &str,
+    ci_mode: bool,
+) -> String {
+    let title = build_title(directory, git_branch, commit_message, base);
+    if !should_validate(directory) {
+        return title;
+    }
+    let Err(error) = validate_title(&title) else {
+        return title;
+    };
+
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    writeln!(handle, "\x1b[1;33mThe PR title is not valid:\x1b[0m {}", error).unwrap_or_default();
+    let _ = handle.flush();
+    if ci_mode {
+        return title;
+    }
+    match Text::new("PR title")
+        .with_default(&title)
+        .with_validator(|input: &str| match validate_title(input) {
+            Ok(_) => Ok(Validation::Valid),
+            Err(e) => Ok(Validation::Invalid(e.into())),
+        })
+        .prompt()
+    {
+        Ok(x) => x,
+        Err(_) => process::exit(1),
+    }
+}
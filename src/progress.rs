@@ -2,53 +2,186 @@ use chrono::{Datelike, Duration, Local, NaiveDate, Weekday};
 use clap::ArgMatches;
 use log::info;
 use regex::Regex;
-use std::collections::HashMap;
-use std::convert::TryInto;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
 use std::io::{self, Write};
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
 
-use crate::types::github_types::{
-    GithubIssue, GithubMilestone, GithubProjectItem, GithubProjectResponse, GithubProjectStatus,
-    Milestone, ProjectContent, ProjectMilestone,
+use crate::progress_clickup::{self, ClickupProgressConfig, CLICKUP_TASK_TYPE};
+use crate::progress_publish;
+use crate::progress_report::{
+    self, MilestoneProgress, ProgressReport, ReportGroup, ReportItem, ReportMetric, ReportSection,
 };
+use crate::storage;
+
+use crate::types::github_types::{GithubProjectItem, GithubProjectResponse};
+
+const DEFAULT_PROJECT_OWNER: &str = "wearebenlabs";
+const DEFAULT_WINDOW_END: &str = "thursday";
+pub const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+const PROJECT_ITEMS_PAGE_SIZE: i64 = 100;
+pub const GITHUB_SOURCE: &str = "github";
+pub const CLICKUP_SOURCE: &str = "clickup";
+pub const PROGRESS_SOURCES: [&str; 2] = [GITHUB_SOURCE, CLICKUP_SOURCE];
+const MILESTONE_GROUP: &str = "milestone";
+pub const GROUP_BY_OPTIONS: [&str; 4] = [MILESTONE_GROUP, "assignee", "repository", "label"];
+const PROGRESS_BUCKET: &str = "progress";
+pub const DONE_BUCKET: &str = "done";
+const BLOCKED_BUCKET: &str = "blocked";
+const SUPPORT_BUCKET: &str = "support";
+const METRIC_LABELS: [(&str, &str); 5] = [
+    ("shipped", "features shipped"),
+    ("progress", "issues being worked on"),
+    ("blocked", "issues blocked"),
+    ("support", "customer issues addressed"),
+    ("closed", "issues closed"),
+];
+
+fn business_write_up(body: &str, rules: &ClassificationRules) -> Vec<String> {
+    match rules.write_up_regex.captures(body) {
+        Some(capture) => capture[1]
+            .split('\n')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect(),
+        None => vec![],
+    }
+}
 
-fn format_issue_display(item: &GithubProjectItem) -> String {
+fn report_item(item: &GithubProjectItem, rules: &ClassificationRules) -> ReportItem {
     let milestone = item
         .milestone
         .as_ref()
         .and_then(|m| m.title.as_ref())
         .unwrap_or(&String::from("No milestone"))
         .clone();
-    let title = item.title.as_deref().unwrap_or("No title");
-    let id = item.content.as_ref().and_then(|c| c.number).unwrap_or(0);
-    let url = item
-        .content
-        .as_ref()
-        .and_then(|c| c.url.as_deref())
-        .unwrap_or("No URL");
-
-    let mut message = format!("- [{}] {} - [#{}]({})\n", milestone, title, id, url);
-    if &item.content.as_ref().is_some_and(|c| c.body.is_some()) == &true {
-        let body = item.clone().content.unwrap().body.unwrap();
-        let re: Regex =
-            Regex::new(r"(?s)BUSINESS WRITE UP START.*?-->(.*?)<!--.*?BUSINESS WRITE UP END")
-                .unwrap();
-        if let Some(capture) = re.captures(&body) {
-            let business_write_up = &capture[1];
-            let lines: Vec<String> = business_write_up
-                .split("\n")
-                .map(|s| s.trim().to_string())
-                .collect();
-
-            for line in lines {
-                if line.len() > 0 {
-                    message.push_str(&format!("> {}\n", line));
-                }
+    ReportItem {
+        title: item.title.clone().unwrap_or("No title".to_owned()),
+        id: match item.content.as_ref() {
+            Some(c) if c.content_type.as_deref() == Some(CLICKUP_TASK_TYPE) => {
+                item.id.clone().unwrap_or_default()
+            }
+            Some(c) => c.number.unwrap_or(0).to_string(),
+            None => "0".to_owned(),
+        },
+        url: item
+            .content
+            .as_ref()
+            .and_then(|c| c.url.clone())
+            .unwrap_or("No URL".to_owned()),
+        milestone,
+        write_up: item
+            .content
+            .as_ref()
+            .and_then(|c| c.body.as_deref())
+            .map(|body| business_write_up(body, rules))
+            .unwrap_or_default(),
+    }
+}
+
+fn report_items(items: Vec<GithubProjectItem>, rules: &ClassificationRules) -> Vec<ReportItem> {
+    sort_by_milestone(items)
+        .iter()
+        .map(|item| report_item(item, rules))
+        .collect()
+}
+
+fn group_names(item: &GithubProjectItem, group_by: &str) -> Vec<String> {
+    let (names, fallback) = match group_by {
+        "assignee" => (item.assignees.clone().unwrap_or_default(), "Unassigned"),
+        "repository" => (
+            item.repository.clone().into_iter().collect(),
+            "No repository",
+        ),
+        "label" => (item.labels.clone().unwrap_or_default(), "No label"),
+        _ => (
+            item.milestone
+                .as_ref()
+                .and_then(|m| m.title.clone())
+                .into_iter()
+                .collect(),
+            "No milestone",
+        ),
+    };
+    match names.is_empty() {
+        true => vec![fallback.to_owned()],
+        false => names,
+    }
+}
+
+fn report_section(
+    key: &str,
+    title: &str,
+    items: Vec<GithubProjectItem>,
+    rules: &ClassificationRules,
+    group_by: Option<&str>,
+) -> ReportSection {
+    let mut groups: BTreeMap<String, Vec<GithubProjectItem>> = BTreeMap::new();
+    if let Some(group_by) = group_by {
+        for item in &items {
+            for name in group_names(item, group_by) {
+                groups.entry(name).or_default().push(item.clone());
             }
         }
     }
+    let mut section = ReportSection::new(key, title, report_items(items, rules));
+    section.groups = groups
+        .into_iter()
+        .map(|(name, items)| ReportGroup {
+            name,
+            items: report_items(items, rules),
+        })
+        .collect();
+    section
+}
 
-    message
+// Done and total items per milestone across every item, not only the ones in the window
+fn milestone_progress(
+    items: &[GithubProjectItem],
+    window: &ReportWindow,
+    rules: &ClassificationRules,
+) -> Vec<MilestoneProgress> {
+    let mut milestones: BTreeMap<String, MilestoneProgress> = BTreeMap::new();
+    for item in items {
+        let Some(milestone) = &item.milestone else {
+            continue;
+        };
+        let Some(title) = &milestone.title else {
+            continue;
+        };
+        let due_on = milestone.due_on.as_ref().map(|d| d.get(..10).unwrap_or(d).to_owned());
+        let days_left = due_on
+            .as_ref()
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            .map(|d| (d - window.end).num_days());
+        let entry = milestones.entry(title.clone()).or_insert(MilestoneProgress {
+            title: title.clone(),
+            done: 0,
+            total: 0,
+            due_on,
+            days_left,
+        });
+        entry.total += 1;
+        let is_done = item
+            .status
+            .as_deref()
+            .and_then(|status| rules.status_bucket(status))
+            == Some(DONE_BUCKET);
+        if is_done {
+            entry.done += 1;
+        }
+    }
+    milestones.into_values().collect()
 }
 
 fn sort_by_milestone(mut items: Vec<GithubProjectItem>) -> Vec<GithubProjectItem> {
@@ -70,119 +203,384 @@ fn sort_by_milestone(mut items: Vec<GithubProjectItem>) -> Vec<GithubProjectItem
     items
 }
 
-pub fn last_thursday() -> NaiveDate {
-    let today = Local::now().date_naive();
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProgressConfig {
+    // Organization or user owning the projects
+    pub owner: Option<String>,
+    // Weekday the report window ends on, e.g. `thursday`
+    pub window_end: Option<String>,
+    pub window_days: Option<i64>,
+    // Project status name to bucket (`progress` or `done`)
+    pub statuses: Option<BTreeMap<String, String>>,
+    // Label name to bucket (`blocked` or `support`)
+    pub labels: Option<BTreeMap<String, String>>,
+    pub date_fields: Option<DateFieldsConfig>,
+    pub write_up: Option<WriteUpConfig>,
+    // `github` (default) or `clickup`
+    pub source: Option<String>,
+    pub clickup: Option<ClickupProgressConfig>,
+    // `milestone`, `assignee`, `repository` or `label`
+    pub group_by: Option<String>,
+}
 
-    // Find how many days to go back to reach Monday of current week
-    let days_to_monday = match today.weekday() {
-        Weekday::Mon => 0,
-        Weekday::Tue => 1,
-        Weekday::Wed => 2,
-        Weekday::Thu => 3,
-        Weekday::Fri => 4,
-        Weekday::Sat => 5,
-        Weekday::Sun => 6,
-    };
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DateFieldsConfig {
+    pub shipped: Option<String>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
 
-    // Get this week's Monday, then go back 4 days to get last Thursday
-    let this_monday = today - Duration::days(days_to_monday);
-    this_monday - Duration::days(4)
+// Text between HTML comments containing these markers is quoted under each item
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WriteUpConfig {
+    pub start: Option<String>,
+    pub end: Option<String>,
 }
 
-pub fn this_thursday() -> NaiveDate {
-    let today = Local::now().date_naive();
+pub struct ClassificationRules {
+    pub statuses: BTreeMap<String, String>,
+    pub labels: BTreeMap<String, String>,
+    pub shipped_field: String,
+    pub start_field: String,
+    pub end_field: String,
+    pub write_up_regex: Regex,
+}
 
-    // Find how many days to go back to reach Monday of current week
-    let days_to_monday = match today.weekday() {
-        Weekday::Mon => 0,
-        Weekday::Tue => 1,
-        Weekday::Wed => 2,
-        Weekday::Thu => 3,
-        Weekday::Fri => 4,
-        Weekday::Sat => 5,
-        Weekday::Sun => 6,
-    };
+impl ClassificationRules {
+    pub fn from_config(config: &ProgressConfig) -> ClassificationRules {
+        let statuses = config.statuses.clone().unwrap_or(BTreeMap::from([
+            ("In Progress".to_owned(), PROGRESS_BUCKET.to_owned()),
+            ("Done".to_owned(), DONE_BUCKET.to_owned()),
+        ]));
+        let labels = config.labels.clone().unwrap_or(BTreeMap::from([
+            ("blocked".to_owned(), BLOCKED_BUCKET.to_owned()),
+            ("support".to_owned(), SUPPORT_BUCKET.to_owned()),
+        ]));
+        let date_fields = config.date_fields.clone().unwrap_or_default();
+        let write_up = config.write_up.clone().unwrap_or_default();
+        let write_up_regex = Regex::new(&format!(
+            r"(?s){}.*?-->(.*?)<!--.*?{}",
+            regex::escape(write_up.start.as_deref().unwrap_or("BUSINESS WRITE UP START")),
+            regex::escape(write_up.end.as_deref().unwrap_or("BUSINESS WRITE UP END"))
+        ))
+        .unwrap();
+        ClassificationRules {
+            statuses,
+            labels,
+            shipped_field: date_fields.shipped.unwrap_or("shipped date".to_owned()),
+            start_field: date_fields.start.unwrap_or("start date".to_owned()),
+            end_field: date_fields.end.unwrap_or("end date".to_owned()),
+            write_up_regex,
+        }
+    }
 
-    // Get this week's Monday, then add 3 days to get this Thursday
-    let this_monday = today - Duration::days(days_to_monday);
-    this_monday + Duration::days(3)
-}
+    pub fn status_bucket(&self, status: &str) -> Option<&str> {
+        self.statuses
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(status))
+            .map(|(_, bucket)| bucket.as_str())
+    }
 
-pub fn next_thursday() -> NaiveDate {
-    let thursday = this_thursday();
-    thursday + Duration::days(7)
-}
-pub fn this_thursday_string() -> String {
-    let thursday = this_thursday();
+    pub fn status_for_bucket(&self, bucket: &str) -> Option<&str> {
+        self.statuses
+            .iter()
+            .find(|(_, b)| b.as_str() == bucket)
+            .map(|(name, _)| name.as_str())
+    }
 
-    thursday.format("%Y-%m-%d").to_string()
+    pub fn has_label_bucket(&self, labels: &Option<Vec<String>>, bucket: &str) -> bool {
+        labels.as_ref().is_some_and(|labels| {
+            labels.iter().any(|label| {
+                self.labels
+                    .iter()
+                    .any(|(name, b)| b == bucket && name.eq_ignore_ascii_case(label))
+            })
+        })
+    }
+
+    pub fn date(&self, item: &GithubProjectItem, field: &str) -> Option<NaiveDate> {
+        item.field(field)
+            .and_then(|d| NaiveDate::parse_from_str(d.get(..10).unwrap_or(&d), "%Y-%m-%d").ok())
+    }
 }
 
-pub fn last_thursday_string() -> String {
-    let last_thursday = last_thursday();
+pub struct ReportWindow {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    // End of the following window, used for the next week focus
+    pub next_end: NaiveDate,
+}
 
-    last_thursday.format("%Y-%m-%d").to_string()
+impl ReportWindow {
+    pub fn title(&self) -> String {
+        match (self.end - self.start).num_days() {
+            7 => format!("Week of {}", self.end.format("%Y-%m-%d")),
+            _ => format!(
+                "{} to {}",
+                self.start.format("%Y-%m-%d"),
+                self.end.format("%Y-%m-%d")
+            ),
+        }
+    }
 }
 
-pub async fn progress(matches: ArgMatches<'static>) {
-    info!("Progress function called");
+// The given weekday in the current Monday to Sunday week
+pub fn weekday_this_week(weekday: Weekday) -> NaiveDate {
+    let today = Local::now().date_naive();
+    let this_monday = today - Duration::days(today.weekday().num_days_from_monday().into());
+    this_monday + Duration::days(weekday.num_days_from_monday().into())
+}
 
-    let stdout = io::stdout(); // get the global stdout entity
-    let mut handle = io::BufWriter::new(&stdout); // optional: wrap that handle in a buffer
-    let mut progress_output = "```{markdown}".to_owned();
-    let projects_str = matches
-        .value_of("projects")
-        .expect("projects should be provide");
-    let projects: Vec<String> = projects_str
-        .split(',')
-        .map(|s| s.trim().to_string())
-        .collect();
+fn parse_date_arg(value: &str, name: &str) -> NaiveDate {
+    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(x) => x,
+        Err(_) => {
+            let stdout = io::stdout();
+            let mut handle = io::BufWriter::new(&stdout);
+            writeln!(handle, "\x1b[1;31m--{} should be a YYYY-MM-DD date\x1b[0m", name)
+                .unwrap_or_default();
+            let _ = handle.flush();
+            process::exit(1);
+        }
+    }
+}
 
-    let date_to_use = this_thursday_string();
+pub fn report_window(matches: &ArgMatches, config: &ProgressConfig) -> ReportWindow {
+    let window_end = matches
+        .value_of("window_end")
+        .map(|w| w.to_owned())
+        .or(config.window_end.clone())
+        .unwrap_or(DEFAULT_WINDOW_END.to_owned());
+    // The flag is checked by clap, only the config value can be unknown here
+    let weekday = match window_end.parse::<Weekday>() {
+        Ok(x) => x,
+        Err(_) => {
+            let stdout = io::stdout();
+            let mut handle = io::BufWriter::new(&stdout);
+            writeln!(
+                handle,
+                "\x1b[1;31mUnknown weekday {} for window_end, use one of {}\x1b[0m",
+                window_end,
+                WEEKDAYS.join(", ")
+            )
+            .unwrap_or_default();
+            let _ = handle.flush();
+            process::exit(1);
+        }
+    };
+    let window_days = matches
+        .value_of("window_days")
+        .and_then(|d| d.parse::<i64>().ok())
+        .or(config.window_days)
+        .filter(|d| *d > 0)
+        .unwrap_or(7);
+
+    let since = matches.value_of("since").map(|d| parse_date_arg(d, "since"));
+    let until = matches.value_of("until").map(|d| parse_date_arg(d, "until"));
+    let (start, end) = match (since, until) {
+        (Some(since), Some(until)) => (since, until),
+        (Some(since), None) => (since, since + Duration::days(window_days)),
+        (None, Some(until)) => (until - Duration::days(window_days), until),
+        (None, None) => {
+            let end = weekday_this_week(weekday);
+            (end - Duration::days(window_days), end)
+        }
+    };
+    ReportWindow {
+        start,
+        end,
+        next_end: end + (end - start),
+    }
+}
 
-    let mut milestone_issues: Vec<GithubProjectItem> = Vec::new();
-    for project in &projects {
+// `gh project item-list` has no cursor, so the limit is raised to the total count when needed
+fn fetch_project_items(project: &str, owner: &str) -> Vec<GithubProjectItem> {
+    let mut limit: i64 = PROJECT_ITEMS_PAGE_SIZE;
+    loop {
         let output = Command::new("gh")
             .arg("project")
             .arg("item-list")
             .arg(project)
             .arg("--owner")
-            .arg("wearebenlabs")
+            .arg(owner)
             .arg("--format=json")
             .arg("-L")
-            .arg("100")
+            .arg(limit.to_string())
             .output()
             .expect("Failed to execute gh project item-list command");
 
-        if output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            match serde_json::from_str::<GithubProjectResponse>(&stdout) {
-                Ok(response) => {
-                    if let Some(temp_items) = response.items {
-                        milestone_issues.extend(temp_items.clone());
-                        info!(
-                            "Successfully fetched {} items from milestone {}",
-                            temp_items.len(),
-                            "11",
-                        );
-                    }
-                }
-                Err(e) => {
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            info!(
+                "Github project item-list command failed for project {}: {}",
+                project, stderr
+            );
+            return vec![];
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        match serde_json::from_str::<GithubProjectResponse>(&stdout) {
+            Ok(response) => {
+                let items = response.items.unwrap_or_default();
+                let total_count: i64 = response.total_count.unwrap_or(0).into();
+                if total_count > limit && items.len() as i64 >= limit {
                     info!(
-                        "Failed to parse project items JSON for milestone {}: {}",
-                        "11", e
+                        "Project {} has {} items, fetching again with a higher limit",
+                        project, total_count
                     );
+                    limit = total_count;
+                    continue;
                 }
+                info!(
+                    "Successfully fetched {} items from project {}",
+                    items.len(),
+                    project,
+                );
+                return items;
+            }
+            Err(e) => {
+                info!(
+                    "Failed to parse project items JSON for project {}: {}",
+                    project, e
+                );
+                return vec![];
+            }
+        }
+    }
+}
+
+pub fn progress_config(directory: &str) -> ProgressConfig {
+    storage::load_repo_config(directory)
+        .progress
+        .unwrap_or_default()
+}
+
+// Cycle time, throughput, carry-over and overdue items for the window
+fn flow_metrics(
+    items: &[GithubProjectItem],
+    shipped_issues: &[GithubProjectItem],
+    window: &ReportWindow,
+    rules: &ClassificationRules,
+) -> Vec<ReportMetric> {
+    let mut metrics = vec![];
+
+    let cycle_times: Vec<i64> = shipped_issues
+        .iter()
+        .filter_map(|item| {
+            let start = rules.date(item, &rules.start_field)?;
+            let shipped = rules.date(item, &rules.shipped_field)?;
+            Some((shipped - start).num_days()).filter(|d| *d >= 0)
+        })
+        .collect();
+    if !cycle_times.is_empty() {
+        let average = cycle_times.iter().sum::<i64>() as f64 / cycle_times.len() as f64;
+        metrics.push(ReportMetric::new(
+            "cycle_time",
+            "days average cycle time",
+            (average * 10.0).round() / 10.0,
+        ));
+    }
+
+    let weeks = ((window.end - window.start).num_days() as f64 / 7.0).max(1.0 / 7.0);
+    metrics.push(ReportMetric::new(
+        "throughput",
+        "features shipped per week",
+        (shipped_issues.len() as f64 / weeks * 10.0).round() / 10.0,
+    ));
+
+    let status_bucket = |item: &GithubProjectItem| {
+        item.status
+            .as_deref()
+            .and_then(|status| rules.status_bucket(status))
+            .map(|b| b.to_owned())
+    };
+    let carry_over = items
+        .iter()
+        .filter(|item| status_bucket(item).as_deref() == Some(PROGRESS_BUCKET))
+        .filter(|item| rules.date(item, &rules.start_field).is_some_and(|d| d < window.start))
+        .count();
+    metrics.push(ReportMetric::new(
+        "carry_over",
+        "issues carried over from previous windows",
+        carry_over as f64,
+    ));
+
+    let overdue = items
+        .iter()
+        .filter(|item| status_bucket(item).as_deref() != Some(DONE_BUCKET))
+        .filter(|item| rules.date(item, &rules.end_field).is_some_and(|d| d < window.end))
+        .count();
+    metrics.push(ReportMetric::new("overdue", "issues overdue", overdue as f64));
+    metrics
+}
+
+// FNV-1a, so the key stays the same between builds unlike `DefaultHasher`
+fn selection_hash(selection: &[String]) -> String {
+    let mut sorted = selection.to_vec();
+    sorted.sort();
+    let hash = sorted
+        .join("\n")
+        .bytes()
+        .fold(0x811c9dc5_u32, |hash, byte| {
+            (hash ^ byte as u32).wrapping_mul(0x01000193)
+        });
+    format!("{:08x}", hash)
+}
+
+// Reports over different projects, sources or groupings are kept apart so deltas compare the
+// same items
+fn snapshot_key(owner: &str, selection: &[String]) -> String {
+    format!("{}-{}", owner, selection_hash(selection))
+}
+
+fn snapshot_directory(directory: &str, owner: &str) -> PathBuf {
+    Path::new(directory)
+        .join(".commit_message")
+        .join("progress")
+        .join(owner)
+}
+
+// The latest snapshot of a window ending before this one
+pub fn load_previous_snapshot(directory: &str, owner: &str, window: &ReportWindow) -> Option<ProgressReport> {
+    let end = window.end.format("%Y-%m-%d").to_string();
+    let mut snapshots: Vec<PathBuf> = fs::read_dir(snapshot_directory(directory, owner))
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_stem()
+                .and_then(|s| s.to_str())
+                .is_some_and(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").is_ok() && *s < *end.as_str())
+        })
+        .collect();
+    snapshots.sort();
+    let previous = snapshots.last()?;
+    info!("Comparing with snapshot {:?}", previous);
+    serde_json::from_str(&fs::read_to_string(previous).ok()?).ok()
+}
+
+pub fn save_snapshot(directory: &str, owner: &str, report: &ProgressReport) {
+    let path = snapshot_directory(directory, owner);
+    if fs::create_dir_all(&path).is_err() {
+        info!("Could not create the snapshot directory {:?}", path);
+        return;
+    }
+    let file_path = path.join(format!("{}.json", report.end));
+    match serde_json::to_string_pretty(report) {
+        Ok(x) => {
+            if let Err(e) = fs::write(&file_path, x) {
+                info!("Could not save the snapshot {:?}: {}", file_path, e);
             }
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            info!(
-                "Github project item-list command failed for milestone {}: {}",
-                "11", stderr
-            );
         }
+        Err(e) => info!("Could not serialize the snapshot: {}", e),
     }
+}
 
+pub fn build_report(
+    milestone_issues: Vec<GithubProjectItem>,
+    window: &ReportWindow,
+    rules: &ClassificationRules,
+    group_by: Option<&str>,
+) -> ProgressReport {
     let mut blocked_issues: Vec<GithubProjectItem> = Vec::new();
     let mut status_counts: HashMap<&str, i32> = HashMap::from([
         ("closed", 0),
@@ -195,134 +593,209 @@ pub async fn progress(matches: ArgMatches<'static>) {
     let mut in_progress_issues: Vec<GithubProjectItem> = Vec::new();
     let mut next_week_issues: Vec<GithubProjectItem> = Vec::new();
     for milestone_item in &milestone_issues {
-        if let Some(labels) = &milestone_item.labels {
-            if labels.contains(&"blocked".to_string()) {
-                blocked_issues.push(milestone_item.clone());
-                *status_counts.get_mut("blocked").unwrap() += 1;
-                continue;
-            }
+        if rules.has_label_bucket(&milestone_item.labels, BLOCKED_BUCKET) {
+            blocked_issues.push(milestone_item.clone());
+            *status_counts.get_mut("blocked").unwrap() += 1;
+            continue;
         }
-        let this_thursday_date = this_thursday();
-        let last_thursday_date = last_thursday();
-        let next_thursday_date = next_thursday();
-
-        if let Some(status) = &milestone_item.status {
-            if status == "In Progress" {
-                *status_counts.get_mut("progress").unwrap() += 1;
-                in_progress_issues.push(milestone_item.clone());
-            }
-            if status == "Done" {
-                if milestone_item.shipped_date.as_ref().is_some() {
-                    let shipped_date = chrono::NaiveDate::parse_from_str(
-                        milestone_item
-                            .shipped_date
-                            .as_ref()
-                            .expect("should be a string"),
-                        "%Y-%m-%d",
-                    )
-                    .expect("should have a string date");
-
-                    if shipped_date >= last_thursday_date && shipped_date <= this_thursday_date {
-                        closed_issues.push(milestone_item.clone());
-                        *status_counts.get_mut("shipped").unwrap() += 1;
-                        continue;
-                    }
+        let status_bucket = milestone_item
+            .status
+            .as_deref()
+            .and_then(|status| rules.status_bucket(status));
+        if status_bucket == Some(PROGRESS_BUCKET) {
+            *status_counts.get_mut("progress").unwrap() += 1;
+            in_progress_issues.push(milestone_item.clone());
+        }
+        if status_bucket == Some(DONE_BUCKET) {
+            match rules.date(milestone_item, &rules.shipped_field) {
+                Some(shipped_date) if shipped_date >= window.start && shipped_date <= window.end => {
+                    closed_issues.push(milestone_item.clone());
+                    *status_counts.get_mut("shipped").unwrap() += 1;
+                    continue;
                 }
-                if milestone_item.shipped_date.as_ref().is_none() {
+                Some(_) => {}
+                None => {
                     *status_counts.get_mut("closed").unwrap() += 1;
                     continue;
                 }
-                if let Some(labels) = &milestone_item.labels {
-                    if labels.contains(&"support".to_string()) {
-                        *status_counts.get_mut("support").unwrap() += 1;
-                        continue;
-                    }
-                }
-            }
-        }
-
-        let mut should_add_to_next_week = false;
-
-        if let Some(end_date_str) = &milestone_item.end_date {
-            if let Ok(end_date) = chrono::NaiveDate::parse_from_str(end_date_str, "%Y-%m-%d") {
-                if end_date > this_thursday_date && end_date <= next_thursday_date {
-                    should_add_to_next_week = true;
-                }
             }
-        }
-        if let Some(start_date_str) = &milestone_item.start_date {
-            if let Ok(start_date) = chrono::NaiveDate::parse_from_str(start_date_str, "%Y-%m-%d") {
-                if start_date > this_thursday_date && start_date <= next_thursday_date {
-                    should_add_to_next_week = true;
-                }
+            if rules.has_label_bucket(&milestone_item.labels, SUPPORT_BUCKET) {
+                *status_counts.get_mut("support").unwrap() += 1;
+                continue;
             }
         }
-        if should_add_to_next_week {
+
+        let is_next_window = |date: Option<NaiveDate>| {
+            date.is_some_and(|d| d > window.end && d <= window.next_end)
+        };
+        if is_next_window(rules.date(milestone_item, &rules.end_field))
+            || is_next_window(rules.date(milestone_item, &rules.start_field))
+        {
             next_week_issues.push(milestone_item.clone());
         }
     }
 
-    progress_output.push_str(&format!(
-        "\n## Week of {}\n### 🚢 Shipped Features\n",
-        &date_to_use
-    ));
-    let sorted_closed_issues = sort_by_milestone(closed_issues);
-    for item in &sorted_closed_issues {
-        let formatted_issue = format_issue_display(item);
-
-        progress_output.push_str(&formatted_issue);
-    }
-
-    let shipped_len: i8 = sorted_closed_issues.len().try_into().unwrap_or(0);
-    let closed_len: i8 = status_counts["closed"].try_into().unwrap_or(0);
+    let shipped_issues = closed_issues.clone();
+    let closed_len = status_counts["closed"];
+    let mut shipped_section = report_section(
+        "shipped",
+        "🚢 Shipped Features",
+        closed_issues,
+        rules,
+        group_by,
+    );
+    let shipped_len = shipped_section.items.len() as i32;
     if shipped_len < closed_len {
-        let diff = closed_len - shipped_len;
-        progress_output.push_str("> [!NOTE]\n");
-        progress_output.push_str(&format!(
-            "> Not displaying {} closed issues not considered user facing features\n",
-            diff,
+        shipped_section.notes.push(format!(
+            "Not displaying {} closed issues not considered user facing features",
+            closed_len - shipped_len,
         ));
     }
 
-    progress_output.push_str(&"\n### 🏗️ In Progress\n");
-    let sorted_in_progress_issues = sort_by_milestone(in_progress_issues);
-    for item in &sorted_in_progress_issues {
-        let formatted_issue = format_issue_display(item);
+    let mut metrics: Vec<ReportMetric> = METRIC_LABELS
+        .iter()
+        .map(|(key, label)| ReportMetric::new(key, label, status_counts[key].into()))
+        .collect();
+    metrics.extend(flow_metrics(&milestone_issues, &shipped_issues, window, rules));
+    let milestones = match group_by {
+        Some(MILESTONE_GROUP) => milestone_progress(&milestone_issues, window, rules),
+        _ => vec![],
+    };
 
-        progress_output.push_str(&formatted_issue);
+    ProgressReport {
+        title: window.title(),
+        start: window.start.format("%Y-%m-%d").to_string(),
+        end: window.end.format("%Y-%m-%d").to_string(),
+        sections: vec![
+            shipped_section,
+            report_section("progress", "🏗️ In Progress", in_progress_issues, rules, group_by),
+            report_section("blocked", "🚧 Blockers/Needs", blocked_issues, rules, group_by),
+            report_section("next", "🎯 Next Week Focus", next_week_issues, rules, group_by),
+        ],
+        milestones,
+        metrics,
     }
+}
 
-    progress_output.push_str(&"\n### 🚧 Blockers/Needs\n");
-    let sorted_blocked_issues = sort_by_milestone(blocked_issues);
-    for item in &sorted_blocked_issues {
-        let formatted_issue = format_issue_display(item);
-        progress_output.push_str(&formatted_issue);
-    }
+pub async fn progress(matches: ArgMatches<'static>, directory: &str) {
+    info!("Progress function called");
 
-    progress_output.push_str(&"\n### 🎯 Next Week Focus\n");
-    let sorted_next_week_issues = sort_by_milestone(next_week_issues);
-    for item in &sorted_next_week_issues {
-        let formatted_issue = format_issue_display(item);
-        progress_output.push_str(&formatted_issue);
-    }
+    let stdout = io::stdout(); // get the global stdout entity
+    let mut handle = io::BufWriter::new(&stdout); // optional: wrap that handle in a buffer
+    let projects_str = matches
+        .value_of("projects")
+        .expect("projects should be provide");
+    let projects: Vec<String> = projects_str
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .collect();
 
-    progress_output.push_str(&"\n### 📊 Metrics\n");
-    for (key, value) in &status_counts {
-        if *value == 0 {
-            continue;
+    let config = progress_config(directory);
+    let owner = matches
+        .value_of("owner")
+        .map(|o| o.to_owned())
+        .or(config.owner.clone())
+        .unwrap_or(DEFAULT_PROJECT_OWNER.to_owned());
+    let window = report_window(&matches, &config);
+    info!(
+        "Progress for {} from {} to {}",
+        owner, window.start, window.end
+    );
+
+    let rules = ClassificationRules::from_config(&config);
+    let source = matches
+        .value_of("source")
+        .map(|s| s.to_owned())
+        .or(config.source.clone())
+        .unwrap_or(GITHUB_SOURCE.to_owned());
+
+    let mut milestone_issues: Vec<GithubProjectItem> = Vec::new();
+    match source.as_str() {
+        CLICKUP_SOURCE => {
+            let (Some(api_key), Some(workspace_id)) = (
+                matches.value_of("clickup_api_key"),
+                matches.value_of("clickup_workspace_id"),
+            ) else {
+                writeln!(
+                    handle,
+                    "\x1b[1;31mCLICKUP_API_KEY and CLICKUP_WORKSPACE_ID are required for ClickUp reports\x1b[0m"
+                )
+                .unwrap_or_default();
+                let _ = handle.flush();
+                process::exit(1);
+            };
+            let clickup_config = config.clickup.clone().unwrap_or_default();
+            match progress_clickup::fetch_clickup_items(
+                directory,
+                api_key,
+                workspace_id,
+                &clickup_config,
+                &rules,
+            )
+            .await
+            {
+                Ok(items) => milestone_issues.extend(items),
+                Err(e) => {
+                    writeln!(handle, "\x1b[1;31mCould not fetch ClickUp tasks:\x1b[0m {}", e)
+                        .unwrap_or_default();
+                    let _ = handle.flush();
+                    process::exit(1);
+                }
+            }
         }
-        let metric_entry_label = match *key {
-            "progress" => "issues being worked on",
-            "support" => "customer issues addressed",
-            "blocked" => "issues blocked",
-            "closed" => "issues closed",
-            "shipped" => "features shipped",
-            _ => key,
-        };
+        _ => {
+            for project in &projects {
+                milestone_issues.extend(fetch_project_items(project, &owner));
+            }
+        }
+    }
 
-        progress_output.push_str(&format!("- {} {}\n", value, metric_entry_label));
+    let group_by = matches
+        .value_of("group_by")
+        .map(|g| g.to_owned())
+        .or(config.group_by.clone());
+    let mut selection: Vec<String> =
+        vec![format!("group_by:{}", group_by.as_deref().unwrap_or(""))];
+    let snapshot_owner = match source.as_str() {
+        CLICKUP_SOURCE => {
+            let clickup_config = config.clickup.clone().unwrap_or_default();
+            for space_id in clickup_config.space_ids.unwrap_or_default() {
+                selection.push(format!("space:{}", space_id));
+            }
+            for list_id in clickup_config.list_ids.unwrap_or_default() {
+                selection.push(format!("list:{}", list_id));
+            }
+            format!(
+                "clickup-{}",
+                matches.value_of("clickup_workspace_id").unwrap_or("")
+            )
+        }
+        _ => {
+            for project in &projects {
+                selection.push(format!("project:{}", project));
+            }
+            owner.clone()
+        }
+    };
+    let snapshot_key = snapshot_key(&snapshot_owner, &selection);
+    let mut report = build_report(milestone_issues, &window, &rules, group_by.as_deref());
+    if let Some(previous) = load_previous_snapshot(directory, &snapshot_key, &window) {
+        report.compare_with(&previous);
+    }
+    if !matches.is_present("no_snapshot") && !matches.is_present("dry_run") {
+        save_snapshot(directory, &snapshot_key, &report);
+    }
+    let format = match matches.value_of("format") {
+        Some(x) => x,
+        None if matches.is_present("slack_formatting") => progress_report::SLACK_FORMAT,
+        None => progress_report::MARKDOWN_FORMAT,
+    };
+    // When posting, the terminal output is only shown if a format was asked for
+    if !matches.is_present("post") || matches.value_of("format").is_some() {
+        writeln!(handle, "{}", progress_report::render_report(&report, format)).unwrap_or_default();
+        let _ = handle.flush();
+    }
+    if matches.is_present("post") && !progress_publish::publish_report(&report, &matches).await {
+        process::exit(1);
     }
-    progress_output.push_str("```\n");
-    write!(handle, "{}", progress_output).unwrap_or_default();
-    let _ = handle.flush();
 }
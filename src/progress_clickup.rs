@@ -0,0 +1,206 @@
+use chrono::DateTime;
+use log::{debug, info};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{
+    progress::{ClassificationRules, DONE_BUCKET},
+    storage,
+    types::github_types::{GithubProjectItem, ProjectContent, ProjectMilestone},
+    utils::extract_clickup_spaces_data::make_clickup_request,
+};
+
+pub const CLICKUP_TASK_TYPE: &str = "ClickupTask";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ClickupProgressConfig {
+    // Defaults to every space cached by `ticket spaces`
+    pub space_ids: Option<Vec<String>>,
+    pub list_ids: Option<Vec<String>>,
+}
+
+// ClickUp dates are milliseconds since the epoch, as strings or numbers
+fn clickup_date(value: &serde_json::Value) -> Option<String> {
+    let milliseconds = match value {
+        serde_json::Value::String(x) => x.parse::<i64>().ok()?,
+        serde_json::Value::Number(x) => x.as_i64()?,
+        _ => return None,
+    };
+    DateTime::from_timestamp_millis(milliseconds).map(|d| d.format("%Y-%m-%d").to_string())
+}
+
+fn string_field(task: &serde_json::Value, key: &str) -> Option<String> {
+    task.get(key).and_then(|v| v.as_str()).map(|v| v.to_owned())
+}
+
+fn names(task: &serde_json::Value, key: &str, name_key: &str) -> Vec<String> {
+    task.get(key)
+        .and_then(|v| v.as_array())
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|v| v.get(name_key).and_then(|n| n.as_str()))
+                .map(|n| n.to_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Maps a task into a project item so it goes through the same buckets and formatters
+pub fn task_to_item(task: &serde_json::Value, rules: &ClassificationRules) -> GithubProjectItem {
+    let mut fields: HashMap<String, serde_json::Value> = HashMap::new();
+    for (name, key) in [
+        ("start date", "start_date"),
+        ("end date", "due_date"),
+        ("shipped date", "date_done"),
+    ] {
+        if let Some(date) = task.get(key).and_then(clickup_date) {
+            fields.insert(name.to_owned(), serde_json::Value::String(date));
+        }
+    }
+    // Custom date fields take precedence over the built in ones with the same name
+    if let Some(custom_fields) = task.get("custom_fields").and_then(|c| c.as_array()) {
+        for custom_field in custom_fields {
+            let name = custom_field.get("name").and_then(|n| n.as_str());
+            let is_date = custom_field.get("type").and_then(|t| t.as_str()) == Some("date");
+            let value = custom_field.get("value").and_then(clickup_date);
+            if let (Some(name), true, Some(value)) = (name, is_date, value) {
+                fields.insert(name.to_lowercase(), serde_json::Value::String(value));
+            }
+        }
+    }
+
+    let status_name = task
+        .get("status")
+        .and_then(|s| s.get("status"))
+        .and_then(|s| s.as_str())
+        .unwrap_or("")
+        .to_owned();
+    let status_type = task
+        .get("status")
+        .and_then(|s| s.get("type"))
+        .and_then(|s| s.as_str())
+        .unwrap_or("");
+    // Closed statuses are done even when they aren't named in the rules
+    let status = match rules.status_bucket(&status_name) {
+        Some(_) => status_name,
+        None if status_type == "closed" || status_type == "done" => rules
+            .status_for_bucket(DONE_BUCKET)
+            .unwrap_or(&status_name)
+            .to_owned(),
+        None => status_name,
+    };
+
+    let list_name = task
+        .get("list")
+        .and_then(|l| l.get("name"))
+        .and_then(|n| n.as_str())
+        .map(|n| n.to_owned());
+    let take_field = |fields: &mut HashMap<String, serde_json::Value>, name: &str| {
+        fields
+            .remove(name)
+            .and_then(|v| v.as_str().map(|v| v.to_owned()))
+    };
+    let start_date = take_field(&mut fields, "start date");
+    let end_date = take_field(&mut fields, "end date");
+    let shipped_date = take_field(&mut fields, "shipped date");
+
+    GithubProjectItem {
+        assignees: Some(names(task, "assignees", "username")),
+        content: Some(ProjectContent {
+            body: string_field(task, "markdown_description").or(string_field(task, "text_content")),
+            number: None,
+            repository: list_name.clone(),
+            title: string_field(task, "name"),
+            content_type: Some(CLICKUP_TASK_TYPE.to_owned()),
+            url: string_field(task, "url"),
+        }),
+        end_date,
+        id: string_field(task, "custom_id").or(string_field(task, "id")),
+        labels: Some(names(task, "tags", "name")),
+        // Lists are the closest thing to a milestone in ClickUp
+        milestone: list_name.clone().map(|title| ProjectMilestone {
+            description: None,
+            due_on: None,
+            title: Some(title),
+        }),
+        repository: list_name,
+        start_date,
+        status: Some(status),
+        title: string_field(task, "name"),
+        shipped_date,
+        fields,
+    }
+}
+
+pub fn configured_space_ids(directory: &str, config: &ClickupProgressConfig) -> Vec<String> {
+    if let Some(space_ids) = &config.space_ids {
+        return space_ids.clone();
+    }
+    match storage::load_clickup_config(directory) {
+        Ok(Some(clickup_config)) => clickup_config
+            .clickup_spaces
+            .unwrap_or_default()
+            .iter()
+            .map(|s| s.id.clone())
+            .collect(),
+        _ => vec![],
+    }
+}
+
+pub async fn fetch_clickup_items(
+    directory: &str,
+    api_key: &str,
+    workspace_id: &str,
+    config: &ClickupProgressConfig,
+    rules: &ClassificationRules,
+) -> Result<Vec<GithubProjectItem>, String> {
+    let mut filters: Vec<String> = configured_space_ids(directory, config)
+        .iter()
+        .map(|id| format!("space_ids[]={}", id))
+        .collect();
+    filters.extend(
+        config
+            .list_ids
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .map(|id| format!("list_ids[]={}", id)),
+    );
+    if filters.is_empty() {
+        return Err(
+            "No ClickUp spaces found. Run `ticket spaces` or set `progress.clickup.space_ids`."
+                .to_owned(),
+        );
+    }
+
+    let client = Client::new();
+    let mut items = vec![];
+    let mut page = 0;
+    loop {
+        let url = format!(
+            "https://api.clickup.com/api/v2/team/{}/task?page={}&include_closed=true&subtasks=true&include_markdown_description=true&{}",
+            workspace_id,
+            page,
+            filters.join("&")
+        );
+        debug!("Fetching {}", url);
+        let body = make_clickup_request(&client, &url, api_key)
+            .await
+            .map_err(|e| e.to_string())?;
+        let tasks = body
+            .get("tasks")
+            .and_then(|t| t.as_array())
+            .cloned()
+            .unwrap_or_default();
+        items.extend(tasks.iter().map(|task| task_to_item(task, rules)));
+        let last_page = body.get("last_page").and_then(|l| l.as_bool()).unwrap_or(true);
+        if tasks.is_empty() || last_page {
+            break;
+        }
+        page += 1;
+    }
+    info!("Fetched {} ClickUp tasks", items.len());
+    Ok(items)
+}
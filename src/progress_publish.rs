@@ -0,0 +1,213 @@
+use clap::ArgMatches;
+use indicatif::ProgressBar;
+use lazy_static::lazy_static;
+use log::{debug, info};
+use regex::Regex;
+use reqwest::Client;
+use serde_json::json;
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use crate::progress_report::{self, ProgressReport};
+
+pub const SLACK_DESTINATION: &str = "slack";
+pub const WEBHOOK_DESTINATION: &str = "webhook";
+pub const GITHUB_DESTINATION: &str = "github";
+pub const DESTINATIONS: [&str; 3] = [SLACK_DESTINATION, WEBHOOK_DESTINATION, GITHUB_DESTINATION];
+
+lazy_static! {
+    static ref DISCUSSION_URL_REGEX: Regex =
+        Regex::new(r"github\.com/(?P<owner>[^/]+)/(?P<repo>[^/]+)/discussions/(?P<number>\d+)")
+            .unwrap();
+}
+
+pub enum Payload {
+    Json { url: String, body: serde_json::Value },
+    GithubComment { target: String, body: String },
+}
+
+impl Payload {
+    pub fn describe(&self) -> String {
+        match self {
+            Payload::Json { url, body } => format!(
+                "POST {}\n{}",
+                url,
+                serde_json::to_string_pretty(body).unwrap_or_default()
+            ),
+            Payload::GithubComment { target, body } => format!("Comment on {}\n{}", target, body),
+        }
+    }
+}
+
+fn required_value(matches: &ArgMatches, name: &str, destination: &str) -> Result<String, String> {
+    matches
+        .value_of(name)
+        .map(|v| v.to_owned())
+        .ok_or(format!(
+            "--{} is required to post to {}",
+            name.replace('_', "-"),
+            destination
+        ))
+}
+
+pub fn build_payload(
+    destination: &str,
+    report: &ProgressReport,
+    matches: &ArgMatches,
+) -> Result<Payload, String> {
+    match destination {
+        SLACK_DESTINATION => Ok(Payload::Json {
+            url: required_value(matches, "slack_webhook_url", destination)?,
+            body: progress_report::slack_blocks(report),
+        }),
+        // Generic webhooks get the full report plus a rendered version for simple consumers
+        WEBHOOK_DESTINATION => Ok(Payload::Json {
+            url: required_value(matches, "webhook_url", destination)?,
+            body: json!({
+                "text": progress_report::render_markdown(report),
+                "report": report,
+            }),
+        }),
+        GITHUB_DESTINATION => Ok(Payload::GithubComment {
+            target: required_value(matches, "github_comment", destination)?,
+            body: progress_report::render_markdown(report),
+        }),
+        _ => Err(format!("Unknown destination {}", destination)),
+    }
+}
+
+async fn post_json(url: &str, body: &serde_json::Value) -> Result<(), Box<dyn std::error::Error>> {
+    let client = Client::new();
+    let bar = ProgressBar::new_spinner();
+    bar.enable_steady_tick(Duration::from_millis(100));
+    let res = client
+        .post(url)
+        .header("User-Agent", "pull_requests")
+        .json(body)
+        .send()
+        .await?;
+    bar.finish();
+
+    let status = res.status();
+    info!("Status: {}", status);
+    if !status.is_success() {
+        let error_body = res.text().await?;
+        return Err(format!("Request failed with status {}: {}", status, error_body).into());
+    }
+    Ok(())
+}
+
+fn run_gh(args: &[&str], stdin_body: Option<&str>) -> Result<String, String> {
+    debug!("Executing gh {:?}", args);
+    let mut child = Command::new("gh")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(body) = stdin_body {
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(body.as_bytes()).map_err(|e| e.to_string())?;
+        }
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+// Discussions are only reachable through GraphQL
+fn comment_on_discussion(owner: &str, repo: &str, number: &str, body: &str) -> Result<(), String> {
+    let discussion_id = run_gh(
+        &[
+            "api",
+            "graphql",
+            "-f",
+            "query=query($owner: String!, $repo: String!, $number: Int!) { repository(owner: $owner, name: $repo) { discussion(number: $number) { id } } }",
+            "-F",
+            &format!("owner={}", owner),
+            "-F",
+            &format!("repo={}", repo),
+            "-F",
+            &format!("number={}", number),
+            "--jq",
+            ".data.repository.discussion.id",
+        ],
+        None,
+    )?;
+    if discussion_id.is_empty() {
+        return Err(format!("Discussion {} not found in {}/{}", number, owner, repo));
+    }
+    run_gh(
+        &[
+            "api",
+            "graphql",
+            "-f",
+            "query=mutation($id: ID!, $body: String!) { addDiscussionComment(input: {discussionId: $id, body: $body}) { comment { url } } }",
+            "-f",
+            &format!("id={}", discussion_id),
+            "-f",
+            &format!("body={}", body),
+        ],
+        None,
+    )
+    .map(|_| ())
+}
+
+pub async fn send_payload(payload: &Payload) -> Result<(), String> {
+    match payload {
+        Payload::Json { url, body } => post_json(url, body).await.map_err(|e| e.to_string()),
+        Payload::GithubComment { target, body } => match DISCUSSION_URL_REGEX.captures(target) {
+            Some(caps) => comment_on_discussion(&caps["owner"], &caps["repo"], &caps["number"], body),
+            // Issue and PR URLs or numbers in the current repository
+            None => run_gh(&["issue", "comment", target, "--body-file", "-"], Some(body)).map(|_| ()),
+        },
+    }
+}
+
+// Returns false if any of the destinations failed
+pub async fn publish_report(report: &ProgressReport, matches: &ArgMatches<'_>) -> bool {
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    let destinations: Vec<&str> = matches.values_of("post").map(|v| v.collect()).unwrap_or_default();
+    let dry_run = matches.is_present("dry_run");
+    let mut succeeded = true;
+
+    for destination in destinations {
+        let payload = match build_payload(destination, report, matches) {
+            Ok(x) => x,
+            Err(e) => {
+                writeln!(handle, "\x1b[1;31m{}\x1b[0m", e).unwrap_or_default();
+                succeeded = false;
+                continue;
+            }
+        };
+        if dry_run {
+            writeln!(handle, "\x1b[1;1m[dry run] {}\x1b[0m\n{}\n", destination, payload.describe())
+                .unwrap_or_default();
+            continue;
+        }
+        match send_payload(&payload).await {
+            Ok(_) => {
+                writeln!(handle, "\x1b[1;32mPosted the report to {}\x1b[0m", destination)
+                    .unwrap_or_default();
+            }
+            Err(e) => {
+                writeln!(
+                    handle,
+                    "\x1b[1;31mCould not post the report to {}:\x1b[0m {}",
+                    destination, e
+                )
+                .unwrap_or_default();
+                succeeded = false;
+            }
+        }
+    }
+    let _ = handle.flush();
+    succeeded
+}
@@ -0,0 +1,413 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+pub const MARKDOWN_FORMAT: &str = "markdown";
+pub const SLACK_FORMAT: &str = "slack";
+pub const SLACK_BLOCKS_FORMAT: &str = "slack-blocks";
+pub const HTML_FORMAT: &str = "html";
+pub const JSON_FORMAT: &str = "json";
+pub const REPORT_FORMATS: [&str; 5] = [
+    MARKDOWN_FORMAT,
+    SLACK_FORMAT,
+    SLACK_BLOCKS_FORMAT,
+    HTML_FORMAT,
+    JSON_FORMAT,
+];
+
+// Slack rejects section blocks with more than 3000 characters
+const SLACK_SECTION_LIMIT: usize = 2900;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReportItem {
+    pub title: String,
+    // Issue number, or task id for ClickUp
+    pub id: String,
+    pub url: String,
+    pub milestone: String,
+    pub write_up: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReportGroup {
+    pub name: String,
+    pub items: Vec<ReportItem>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReportSection {
+    pub key: String,
+    pub title: String,
+    pub items: Vec<ReportItem>,
+    // Only set with `--group-by`. Items can be in more than one group (assignees, labels)
+    #[serde(default)]
+    pub groups: Vec<ReportGroup>,
+    pub notes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MilestoneProgress {
+    pub title: String,
+    pub done: usize,
+    pub total: usize,
+    pub due_on: Option<String>,
+    pub days_left: Option<i64>,
+}
+
+impl MilestoneProgress {
+    pub fn burndown_line(&self) -> String {
+        let percentage = match self.total {
+            0 => 0,
+            total => self.done * 100 / total,
+        };
+        let filled = percentage / 10;
+        let mut line = format!(
+            "{} {}{} {}% ({}/{} done)",
+            self.title,
+            "▓".repeat(filled),
+            "░".repeat(10 - filled),
+            percentage,
+            self.done,
+            self.total
+        );
+        if let (Some(due_on), Some(days_left)) = (&self.due_on, self.days_left) {
+            let remaining = self.total - self.done;
+            match days_left {
+                d if d < 0 => line.push_str(&format!(" · due {}, {} days overdue", due_on, -d)),
+                d => {
+                    let weeks_left = (d as f64 / 7.0).max(1.0 / 7.0);
+                    line.push_str(&format!(
+                        " · due {}, {} days left, {} per week needed",
+                        due_on,
+                        d,
+                        format_number((remaining as f64 / weeks_left * 10.0).round() / 10.0)
+                    ));
+                }
+            }
+        }
+        line
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReportMetric {
+    pub key: String,
+    pub label: String,
+    pub value: f64,
+    // Value in the previous report, when there's a snapshot to compare with
+    pub previous: Option<f64>,
+}
+
+impl ReportMetric {
+    pub fn new(key: &str, label: &str, value: f64) -> ReportMetric {
+        ReportMetric {
+            key: key.to_owned(),
+            label: label.to_owned(),
+            value,
+            previous: None,
+        }
+    }
+
+    pub fn delta(&self) -> Option<f64> {
+        self.previous.map(|previous| self.value - previous)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProgressReport {
+    pub title: String,
+    pub start: String,
+    pub end: String,
+    pub sections: Vec<ReportSection>,
+    #[serde(default)]
+    pub milestones: Vec<MilestoneProgress>,
+    pub metrics: Vec<ReportMetric>,
+}
+
+impl ProgressReport {
+    pub fn compare_with(&mut self, previous: &ProgressReport) {
+        for metric in self.metrics.iter_mut() {
+            metric.previous = previous
+                .metrics
+                .iter()
+                .find(|m| m.key == metric.key)
+                .map(|m| m.value);
+        }
+    }
+}
+
+impl ReportSection {
+    // Ungrouped sections are a single group without a name
+    pub fn item_groups(&self) -> Vec<(Option<String>, &[ReportItem])> {
+        match self.groups.is_empty() {
+            true => vec![(None, self.items.as_slice())],
+            false => self
+                .groups
+                .iter()
+                .map(|g| (Some(format!("{} ({})", g.name, g.items.len())), g.items.as_slice()))
+                .collect(),
+        }
+    }
+
+    pub fn new(key: &str, title: &str, items: Vec<ReportItem>) -> ReportSection {
+        ReportSection {
+            key: key.to_owned(),
+            title: title.to_owned(),
+            items,
+            groups: vec![],
+            notes: vec![],
+        }
+    }
+}
+
+pub fn render_report(report: &ProgressReport, format: &str) -> String {
+    match format {
+        SLACK_FORMAT => render_slack(report),
+        SLACK_BLOCKS_FORMAT => render_slack_blocks(report),
+        HTML_FORMAT => render_html(report),
+        JSON_FORMAT => serde_json::to_string_pretty(report).unwrap_or_default(),
+        _ => render_markdown(report),
+    }
+}
+
+pub fn format_number(value: f64) -> String {
+    match value.fract() == 0.0 {
+        true => format!("{}", value as i64),
+        false => format!("{:.1}", value),
+    }
+}
+
+fn metric_lines(report: &ProgressReport) -> Vec<String> {
+    report
+        .metrics
+        .iter()
+        .filter(|m| m.value != 0.0 || m.delta().is_some_and(|d| d != 0.0))
+        .map(|m| {
+            let mut line = format!("{} {}", format_number(m.value), m.label);
+            if let Some(delta) = m.delta() {
+                let sign = if delta > 0.0 { "+" } else { "" };
+                line.push_str(&format!(" ({}{} vs previous)", sign, format_number(delta)));
+            }
+            line
+        })
+        .collect()
+}
+
+pub fn render_markdown(report: &ProgressReport) -> String {
+    let mut output = format!("## {}\n", report.title);
+    for section in &report.sections {
+        output.push_str(&format!("### {}\n", section.title));
+        for (group, items) in section.item_groups() {
+            if let Some(group) = group {
+                output.push_str(&format!("#### {}\n", group));
+            }
+            for item in items {
+                output.push_str(&format!(
+                    "- [{}] {} - [#{}]({})\n",
+                    item.milestone, item.title, item.id, item.url
+                ));
+                for line in &item.write_up {
+                    output.push_str(&format!("> {}\n", line));
+                }
+            }
+        }
+        if !section.notes.is_empty() {
+            output.push_str("> [!NOTE]\n");
+            for note in &section.notes {
+                output.push_str(&format!("> {}\n", note));
+            }
+        }
+        output.push('\n');
+    }
+    if !report.milestones.is_empty() {
+        output.push_str("### 🗓️ Milestones\n");
+        for milestone in &report.milestones {
+            output.push_str(&format!("- {}\n", milestone.burndown_line()));
+        }
+        output.push('\n');
+    }
+    output.push_str("### 📊 Metrics\n");
+    for line in metric_lines(report) {
+        output.push_str(&format!("- {}\n", line));
+    }
+    output
+}
+
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn slack_section_text(section: &ReportSection) -> String {
+    let mut output = format!("*{}*\n", slack_escape(&section.title));
+    for (group, items) in section.item_groups() {
+        if let Some(group) = group {
+            output.push_str(&format!("_{}_\n", slack_escape(&group)));
+        }
+        for item in items {
+            output.push_str(&format!(
+                "• [{}] <{}|{} #{}>\n",
+                slack_escape(&item.milestone),
+                item.url,
+                slack_escape(&item.title),
+                item.id
+            ));
+            for line in &item.write_up {
+                output.push_str(&format!(">{}\n", slack_escape(line)));
+            }
+        }
+    }
+    if section.items.is_empty() {
+        output.push_str("_Nothing to report_\n");
+    }
+    for note in &section.notes {
+        output.push_str(&format!("_{}_\n", slack_escape(note)));
+    }
+    output
+}
+
+pub fn render_slack(report: &ProgressReport) -> String {
+    let mut output = format!("*{}*\n\n", slack_escape(&report.title));
+    for section in &report.sections {
+        output.push_str(&slack_section_text(section));
+        output.push('\n');
+    }
+    if !report.milestones.is_empty() {
+        output.push_str("*🗓️ Milestones*\n");
+        for milestone in &report.milestones {
+            output.push_str(&format!("• {}\n", slack_escape(&milestone.burndown_line())));
+        }
+        output.push('\n');
+    }
+    output.push_str("*📊 Metrics*\n");
+    for line in metric_lines(report) {
+        output.push_str(&format!("• {}\n", line));
+    }
+    output
+}
+
+// Splits on line boundaries so links aren't cut in half
+fn slack_chunks(text: &str) -> Vec<String> {
+    let mut chunks: Vec<String> = vec![];
+    let mut current = String::new();
+    for line in text.lines() {
+        if current.len() + line.len() + 1 > SLACK_SECTION_LIMIT && !current.is_empty() {
+            chunks.push(current);
+            current = String::new();
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+pub fn slack_blocks(report: &ProgressReport) -> serde_json::Value {
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": { "type": "plain_text", "text": report.title, "emoji": true }
+    })];
+    for section in &report.sections {
+        for chunk in slack_chunks(&slack_section_text(section)) {
+            blocks.push(json!({
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": chunk }
+            }));
+        }
+    }
+    if !report.milestones.is_empty() {
+        let lines: Vec<String> = report
+            .milestones
+            .iter()
+            .map(|m| format!("• {}", slack_escape(&m.burndown_line())))
+            .collect();
+        for chunk in slack_chunks(&format!("*🗓️ Milestones*\n{}", lines.join("\n"))) {
+            blocks.push(json!({
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": chunk }
+            }));
+        }
+    }
+    blocks.push(json!({ "type": "divider" }));
+    let metrics = metric_lines(report);
+    if !metrics.is_empty() {
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{ "type": "mrkdwn", "text": format!("📊 {}", metrics.join(" · ")) }]
+        }));
+    }
+    json!({ "text": report.title, "blocks": blocks })
+}
+
+pub fn render_slack_blocks(report: &ProgressReport) -> String {
+    serde_json::to_string_pretty(&slack_blocks(report)).unwrap_or_default()
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn render_html(report: &ProgressReport) -> String {
+    let mut output = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{}</title></head>\n<body style=\"font-family: sans-serif;\">\n<h2>{}</h2>\n",
+        html_escape(&report.title),
+        html_escape(&report.title)
+    );
+    for section in &report.sections {
+        output.push_str(&format!("<h3>{}</h3>\n", html_escape(&section.title)));
+        for (group, items) in section.item_groups() {
+            if let Some(group) = group {
+                output.push_str(&format!("<h4>{}</h4>\n", html_escape(&group)));
+            }
+            output.push_str("<ul>\n");
+            output.push_str(&html_items(items));
+            output.push_str("</ul>\n");
+        }
+        for note in &section.notes {
+            output.push_str(&format!("<p><em>{}</em></p>\n", html_escape(note)));
+        }
+    }
+    if !report.milestones.is_empty() {
+        output.push_str("<h3>🗓️ Milestones</h3>\n<ul>\n");
+        for milestone in &report.milestones {
+            output.push_str(&format!("<li>{}</li>\n", html_escape(&milestone.burndown_line())));
+        }
+        output.push_str("</ul>\n");
+    }
+    output.push_str("<h3>📊 Metrics</h3>\n<ul>\n");
+    for line in metric_lines(report) {
+        output.push_str(&format!("<li>{}</li>\n", html_escape(&line)));
+    }
+    output.push_str("</ul>\n</body>\n</html>\n");
+    output
+}
+
+fn html_items(items: &[ReportItem]) -> String {
+    let mut output = String::new();
+    for item in items {
+        output.push_str(&format!(
+            "<li>[{}] {} - <a href=\"{}\">#{}</a>",
+            html_escape(&item.milestone),
+            html_escape(&item.title),
+            html_escape(&item.url),
+            item.id
+        ));
+        if !item.write_up.is_empty() {
+            output.push_str(&format!(
+                "<blockquote>{}</blockquote>",
+                item.write_up
+                    .iter()
+                    .map(|l| html_escape(l))
+                    .collect::<Vec<String>>()
+                    .join("<br>")
+            ));
+        }
+        output.push_str("</li>\n");
+    }
+    output
+}
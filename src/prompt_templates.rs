@@ -0,0 +1,230 @@
+use clap::ArgMatches;
+use inquire::{Editor, Select};
+use log::{debug, info};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::prompts;
+
+pub struct PromptTemplate {
+    pub name: &'static str,
+    // Sent to Claude, otherwise the template renders a document like the PR body
+    pub is_prompt: bool,
+    pub description: &'static str,
+    pub variables: &'static [&'static str],
+    pub default_body: &'static str,
+}
+
+pub const COMMIT_MESSAGE_TEMPLATE: &str = "commit_message";
+pub const PR_TEMPLATE_TEMPLATE: &str = "pr_template";
+pub const ISSUE_EXPANSION_TEMPLATE: &str = "issue_expansion";
+pub const PR_BODY_TEMPLATE: &str = "pr_body";
+
+pub const DEFAULT_SCOPES: &str = "web: files related to frontend code - api: files related to backend code - ci: files related to deployments";
+
+pub const PROMPT_TEMPLATES: [PromptTemplate; 4] = [
+    PromptTemplate {
+        name: COMMIT_MESSAGE_TEMPLATE,
+        is_prompt: true,
+        description: "Prompt used by `commit --claude` to build the commit message",
+        variables: &["diff", "allowed_types", "scopes"],
+        default_body: include_str!("templates/commit_message.md"),
+    },
+    PromptTemplate {
+        name: PR_TEMPLATE_TEMPLATE,
+        is_prompt: true,
+        description: "Prompt used to fill the PR template with Claude",
+        variables: &["diff", "scopes"],
+        default_body: include_str!("templates/pr_template.md"),
+    },
+    PromptTemplate {
+        name: ISSUE_EXPANSION_TEMPLATE,
+        is_prompt: true,
+        description: "Prompt used to extend an issue before implementing it. The issue description is piped after it",
+        variables: &["issue_title"],
+        default_body: include_str!("templates/issue_expansion.md"),
+    },
+    PromptTemplate {
+        name: PR_BODY_TEMPLATE,
+        is_prompt: false,
+        description: "PR body template (not sent to Claude) used when the repository has no .github/pull_request_template.md",
+        variables: &[
+            "description",
+            "risk_level",
+            "risk_heading",
+            "risk_description",
+            "test_steps",
+            "issue_id",
+            "checklist",
+        ],
+        default_body: include_str!("templates/pr_body.md"),
+    },
+];
+
+pub fn find_template(name: &str) -> Option<&'static PromptTemplate> {
+    PROMPT_TEMPLATES.iter().find(|t| t.name == name)
+}
+
+// Overrides live next to the branch files so they can be committed with the repository
+pub fn template_override_path(directory: &str, name: &str) -> PathBuf {
+    Path::new(directory)
+        .join(".commit_message")
+        .join("prompts")
+        .join(format!("{}.md", name))
+}
+
+pub fn load_template(directory: &str, name: &str) -> (String, bool) {
+    let override_path = template_override_path(directory, name);
+    if override_path.exists() {
+        match fs::read_to_string(&override_path) {
+            Ok(body) => {
+                info!("Using prompt template override {:?}", override_path);
+                return (body, true);
+            }
+            Err(e) => {
+                info!("Failed to read prompt template override: {}", e);
+            }
+        }
+    }
+    let template = find_template(name).expect("Prompt template should be known");
+    (template.default_body.to_owned(), false)
+}
+
+// Single pass, so `{{...}}` inside a substituted value (e.g. the diff) is left as is
+pub fn render_template(template: &str, variables: &HashMap<&str, String>) -> String {
+    let mut rendered = String::new();
+    let mut rest = template.trim_end();
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after
+            .find("}}")
+            .and_then(|end| variables.get(&after[..end]).map(|value| (end, value)))
+        {
+            Some((end, value)) => {
+                rendered.push_str(value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                rendered.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+pub fn render_prompt(directory: &str, name: &str, variables: &HashMap<&str, String>) -> String {
+    let (template, _) = load_template(directory, name);
+    let rendered = render_template(&template, variables);
+    debug!("Rendered prompt {}: {}", name, rendered);
+    rendered
+}
+
+pub fn allowed_types_variable() -> String {
+    prompts::TYPE_OPTIONS.join(",\n")
+}
+
+fn template_name_from_matches(matches: &ArgMatches<'static>) -> &'static str {
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    let name = match matches.value_of("name") {
+        Some(x) => x.to_owned(),
+        None => {
+            let options: Vec<&str> = PROMPT_TEMPLATES.iter().map(|t| t.name).collect();
+            match Select::new("Select a prompt template", options).prompt() {
+                Ok(x) => x.to_owned(),
+                Err(_) => process::exit(1),
+            }
+        }
+    };
+
+    match find_template(&name) {
+        Some(template) => template.name,
+        None => {
+            writeln!(handle, "\x1b[1;31mUnknown prompt template {}\x1b[0m", name)
+                .unwrap_or_default();
+            let _ = handle.flush();
+            process::exit(1);
+        }
+    }
+}
+
+pub fn prompt_templates(matches: ArgMatches<'static>, directory: &str) {
+    let stdout = io::stdout(); // get the global stdout entity
+    let mut handle = io::BufWriter::new(&stdout); // optional: wrap that handle in a buffer
+
+    match matches.subcommand() {
+        ("list", Some(_)) => {
+            for template in PROMPT_TEMPLATES.iter() {
+                let source = match template_override_path(directory, template.name).exists() {
+                    true => "override",
+                    false => "default",
+                };
+                let kind = match template.is_prompt {
+                    true => "Claude prompt",
+                    false => "PR body template",
+                };
+                writeln!(
+                    handle,
+                    "\x1b[1;1m{}\x1b[0m ({}, {})\n  {}\n  Variables: {}",
+                    template.name,
+                    kind,
+                    source,
+                    template.description,
+                    template
+                        .variables
+                        .iter()
+                        .map(|v| format!("{{{{{}}}}}", v))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+                .unwrap_or_default();
+            }
+        }
+        ("show", Some(arg)) => {
+            let name = template_name_from_matches(arg);
+            let (body, is_override) = load_template(directory, name);
+            if is_override {
+                writeln!(
+                    handle,
+                    "\x1b[1;33mUsing override from {}\x1b[0m\n",
+                    template_override_path(directory, name).display()
+                )
+                .unwrap_or_default();
+            }
+            writeln!(handle, "{}", body).unwrap_or_default();
+        }
+        ("edit", Some(arg)) => {
+            let name = template_name_from_matches(arg);
+            let (body, _) = load_template(directory, name);
+            let edited = match Editor::new(&format!("Edit the {} template", name))
+                .with_predefined_text(&body)
+                .prompt()
+            {
+                Ok(x) => x,
+                Err(_) => process::exit(1),
+            };
+
+            let override_path = template_override_path(directory, name);
+            fs::create_dir_all(override_path.parent().unwrap()).unwrap();
+            fs::write(&override_path, edited).expect("Failed to write prompt template");
+            writeln!(
+                handle,
+                "\x1b[1;32mSaved prompt template to {}\x1b[0m",
+                override_path.display()
+            )
+            .unwrap_or_default();
+        }
+        _ => {
+            writeln!(handle, "Use one of `list`, `show` or `edit`").unwrap_or_default();
+        }
+    }
+    let _ = handle.flush();
+}
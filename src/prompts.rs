@@ -6,8 +6,15 @@ use std::{
     time::Duration,
 };
 
-use crate::{branch_utils, commit};
-use inquire::{Confirm, Editor, Select, Text, formatter::OptionFormatter, validator::Validation};
+use crate::{
+    branch_utils, commit_message,
+    pr_body::{self, PrBody},
+    prompt_templates, scopes,
+    utils::claude,
+};
+use inquire::{
+    Confirm, Editor, MultiSelect, Select, Text, formatter::OptionFormatter, validator::Validation,
+};
 use log::info;
 
 pub fn editor_prompt() {
@@ -109,17 +116,19 @@ pub fn team_prefix_prompt(team_prefix: &str) -> String {
     return selected_team_prefix;
 }
 
+pub const TYPE_OPTIONS: [&str; 8] = [
+    "feat: A new feature",
+    "fix: Bug (feature related) or code (linting, typecheck, etc) fixes",
+    "test: Adding missing tests or correcting existing tests",
+    "refactor: A code change that improves performance or code quality",
+    "docs: Documentation only changes",
+    "build: Changes that affect the build system or external dependencies (example scopes: gulp, broccoli, npm)",
+    "ci: Changes to our CI configuration files and scripts (example scopes: Travis, Circle, BrowserStack, SauceLabs)",
+    "revert: Reverts a previous commit",
+];
+
 pub fn select_types_prompt(proposed_type: Option<String>) -> String {
-    let type_options: Vec<&str> = vec![
-        "feat: A new feature",
-        "fix: Bug (feature related) or code (linting, typecheck, etc) fixes",
-        "test: Adding missing tests or correcting existing tests",
-        "refactor: A code change that improves performance or code quality",
-        "docs: Documentation only changes",
-        "build: Changes that affect the build system or external dependencies (example scopes: gulp, broccoli, npm)",
-        "ci: Changes to our CI configuration files and scripts (example scopes: Travis, Circle, BrowserStack, SauceLabs)",
-        "revert: Reverts a previous commit",
-    ];
+    let type_options: Vec<&str> = TYPE_OPTIONS.to_vec();
 
     fn get_short_type(type_str: &str) -> String {
         let parts = type_str.split(": ").collect::<Vec<&str>>();
@@ -188,17 +197,68 @@ pub fn select_additional_message_prompt() -> Option<String> {
     return Some(message_to_use);
 }
 
-pub fn select_message_prompt(git_branch: &str) -> String {
-    let default_message_name = branch_utils::branch_name(&git_branch);
+pub fn breaking_change_prompt() -> Option<String> {
+    let is_breaking_prompt = Confirm::new("Is this a breaking change?")
+        .with_default(false)
+        .prompt();
+    match is_breaking_prompt {
+        Ok(false) => return None,
+        Ok(true) => {}
+        Err(_) => process::exit(1),
+    };
+
+    let description_prompt = Text::new("Describe the breaking change")
+        .with_validator(|input: &str| {
+            if input.trim().is_empty() {
+                Ok(Validation::Invalid("A description is required".into()))
+            } else {
+                Ok(Validation::Valid)
+            }
+        })
+        .prompt();
+    match description_prompt {
+        Ok(description) => Some(description),
+        Err(_) => process::exit(1),
+    }
+}
+
+pub fn co_authors_prompt(authors: Vec<String>) -> Vec<String> {
+    if authors.is_empty() {
+        return vec![];
+    }
+    let co_authors_prompt = MultiSelect::new("Select co-authors", authors)
+        .with_help_message("Leave empty if there are no co-authors")
+        .prompt();
+    match co_authors_prompt {
+        Ok(co_authors) => co_authors,
+        Err(_) => process::exit(1),
+    }
+}
+
+pub fn select_message_prompt(default_message_name: &str, message_options: Vec<String>) -> String {
+    let write_message = "Write a different message".to_owned();
+    if message_options.len() > 1 {
+        let mut options = message_options.clone();
+        options.push(write_message.clone());
+        match Select::new("Select the commit message", options).prompt() {
+            Ok(message) if message != write_message => return message,
+            Ok(_) => {}
+            Err(_) => {
+                println!("An error happened when selecting the commit message, try again.");
+                std::process::exit(1);
+            }
+        }
+    }
 
     let message_prompt = Text::new("Enter commit message")
         .with_default(&default_message_name)
         .with_validator(|input: &str| {
             let length = input.chars().count();
-            if length > 55 {
+            if length > commit_message::MAX_SUBJECT_LENGTH {
                 Ok(Validation::Invalid(
                     format!(
-                        "Commit message limit is 55 characters. You have {}.",
+                        "Commit message limit is {} characters. You have {}.",
+                        commit_message::MAX_SUBJECT_LENGTH,
                         length
                     )
                     .into(),
@@ -219,6 +279,49 @@ pub fn select_message_prompt(git_branch: &str) -> String {
     return message;
 }
 
+pub fn select_scope_prompt(scope_options: Vec<String>, proposed_scope: Option<String>) -> Option<String> {
+    let no_scope = "(no scope)".to_owned();
+    if scope_options.is_empty() {
+        let scope_prompt = Text::new("Enter commit scope")
+            .with_default(&proposed_scope.unwrap_or_default())
+            .with_help_message("Leave empty to commit without a scope")
+            .prompt();
+        return match scope_prompt {
+            Ok(scope) if scope.trim().is_empty() => None,
+            Ok(scope) => Some(scope.trim().to_owned()),
+            Err(_) => {
+                println!("An error happened when selecting the scope, try again.");
+                std::process::exit(1);
+            }
+        };
+    }
+
+    let mut options = scope_options.clone();
+    if let Some(scope) = &proposed_scope {
+        if !options.contains(scope) {
+            options.insert(0, scope.clone());
+        }
+    }
+    options.push(no_scope.clone());
+    let starting_cursor = match &proposed_scope {
+        Some(scope) => options.iter().position(|x| x == scope).unwrap_or(0),
+        None => options.len() - 1,
+    };
+
+    let selected_scope_prompt = Select::new("Select change scope", options)
+        .with_starting_cursor(starting_cursor)
+        .prompt();
+
+    match selected_scope_prompt {
+        Ok(scope) if scope == no_scope => None,
+        Ok(scope) => Some(scope),
+        Err(_) => {
+            println!("An error happened when selecting the scope, try again.");
+            std::process::exit(1);
+        }
+    }
+}
+
 pub fn commit_pr_prompt(commit_message: Option<&str>) -> bool {
     let commit_message_for_prompt = match commit_message.is_some() {
         true => format!("\x1b[1;31m{}\x1b[0;0m", commit_message.unwrap()),
@@ -258,20 +361,11 @@ pub fn pr_template_prompt(
     let stdout = io::stdout(); // get the global stdout entity
     let mut handle = io::BufWriter::new(&stdout);
 
-    let mut pr_template = "".to_owned();
-    let mut has_description = false;
-
     let mut pr_description_string: Option<String> = None;
     let mut pr_risk_factor_string: Option<String> = None;
     let mut pr_risk_factor_description_string: Option<String> = None;
     let mut pr_test_steps: Option<String> = None;
 
-    let risk_factor_map = HashMap::from([
-        ("High", "🚨HIGH🚨"),
-        ("Medium", "⚠️MEDIUM⚠️"),
-        ("Low", "👍LOW👍"),
-        ("Trivial", "✅TRIVIAL✅"),
-    ]);
     match use_claude {
         true => {
             writeln!(
@@ -291,9 +385,13 @@ pub fn pr_template_prompt(
             //
             // info!("Git diff: {:?}", git_diff);
 
+            let diff_base = branch_utils::diff_base(
+                directory,
+                &branch_utils::base_branch(directory, git_branch),
+            );
             let git_diff_cmd = format!(
-                r#"printf "%q"  $(git --no-pager diff main..{} )"#,
-                &git_branch
+                r#"printf "%q"  $(git --no-pager diff {}..{} )"#,
+                diff_base, &git_branch
             );
             let git_diff = Command::new("sh")
                 .arg("-c")
@@ -307,31 +405,19 @@ pub fn pr_template_prompt(
 
             let bar = ProgressBar::new_spinner();
             bar.enable_steady_tick(Duration::from_millis(100));
-            let cmd_arg = format!(
-                r#"cd {} && claude --model sonnet -p "We have done several changes to this repository.You are a technical writer in charge of documenting the changes and writing the changes to a document.
-The changes are the following:
-                {}
-                Based on these changes, give me the following:
-                pr_description should be a short summary of the changes. Write a paragraph with the main changes and if needed a bullet list with the main changes.
-                pr_risk_factor: should be one of High  Medium  Low  Trivial; choose an option based on how complex the changes were, the potential to break CI/CD deployments, and changes to user experiences.
-                pr_risk_factor_description: based on the selected risk factor describe why the option was selected,
-                pr_test_steps: describe how to manually test this PR and what we should be aware of; ideally mention commands to run, curl requests, etc,
-                pr_scopes: an array of options from web  api  ci; select all applicable based on the cahnges: were they done to the backend code, the frontend code or the deployment process.
-                Return the response as a json object with the following structure:
-                    pr_description: string,
-                    pr_risk_factor: string,
-                    pr_risk_factor_description: string,
-                    pr_test_steps: string,
-                    pr_scopes: [],
-                " --output-format json "#,
-                &directory, &git_diff_stdout_string
+            let prompt_text = prompt_templates::render_prompt(
+                directory,
+                prompt_templates::PR_TEMPLATE_TEMPLATE,
+                &HashMap::from([
+                    ("diff", git_diff_stdout_string.to_owned()),
+                    (
+                        "scopes",
+                        scopes::scopes_prompt_variable(&scopes::configured_scopes(directory)),
+                    ),
+                ]),
             );
-
-            let output = Command::new("sh")
-                .arg("-c")
-                .arg(cmd_arg)
-                .output()
-                .expect("Failed to run  process");
+            let output =
+                claude::run_claude_prompt(directory, &prompt_text).expect("Failed to run  process");
 
             bar.finish();
             info!("Claude PR  {:?}", output);
@@ -363,13 +449,8 @@ The changes are the following:
             let plain_pr_risk_factor_string =
                 final_json.get("pr_risk_factor").unwrap().as_str().unwrap();
 
-            if risk_factor_map.contains_key(&plain_pr_risk_factor_string) {
-                pr_risk_factor_string = Some(
-                    risk_factor_map
-                        .get(&plain_pr_risk_factor_string)
-                        .unwrap()
-                        .to_string(),
-                );
+            if pr_body::RISK_LEVELS.contains(&plain_pr_risk_factor_string) {
+                pr_risk_factor_string = Some(plain_pr_risk_factor_string.to_string());
             }
 
             // pr_risk_factor_string = selected_risk_factor;
@@ -401,17 +482,11 @@ The changes are the following:
             if pr_description.is_some() {
                 pr_description_string = pr_description;
             }
-            let risk_options: Vec<&str> = vec!["High", "Medium", "Low", "Trivial"];
+            let risk_options: Vec<&str> = pr_body::RISK_LEVELS.to_vec();
             let risk_factor_prompt = Select::new("Select risk factor", risk_options).prompt();
 
             let selected_risk_factor = match risk_factor_prompt {
-                Ok(risk_factor) => {
-                    if risk_factor_map.contains_key(&risk_factor) {
-                        Some(risk_factor_map.get(&risk_factor).unwrap().to_string())
-                    } else {
-                        None
-                    }
-                }
+                Ok(risk_factor) => Some(risk_factor.to_string()),
                 _ => None,
             };
             if selected_risk_factor.is_some() {
@@ -441,64 +516,67 @@ The changes are the following:
         }
     }
 
-    if pr_description_string.is_some() {
-        pr_template += &"\n";
-        pr_template += &pr_description_string.unwrap();
-        pr_template += &"\n";
-        has_description = true;
-    } else {
-        pr_template += &"..."
-    }
+    pr_body::render(
+        directory,
+        &PrBody {
+            description: pr_description_string,
+            risk_level: pr_risk_factor_string,
+            risk_description: pr_risk_factor_description_string,
+            test_steps: pr_test_steps,
+            issue_id: issue_id.to_owned(),
+        },
+    )
+}
 
-    if pr_risk_factor_string.is_some() {
-        pr_template += &format!(
-            "\n# 🚦 This is a {} risk PR\n",
-            pr_risk_factor_string.unwrap()
-        );
-    }
+pub const KEEP_LOCAL_OPTION: &str = "Keep the local version";
+pub const KEEP_REMOTE_OPTION: &str = "Keep the Github version";
+pub const KEEP_BOTH_OPTION: &str = "Keep both";
+pub const EDIT_OPTION: &str = "Edit";
 
-    if pr_risk_factor_description_string.is_some() {
-        pr_template += &"\n";
-        pr_template += &pr_risk_factor_description_string.unwrap();
-        pr_template += &"\n";
-    } else {
-        pr_template += &"Because...";
-        pr_template += &"\n";
-    }
+pub fn merge_conflict_prompt(local: &[String], remote: &[String]) -> Vec<String> {
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    writeln!(
+        handle,
+        "\x1b[1;33mThe PR body was changed locally and on Github\x1b[0m\n\x1b[1;1mLocal:\x1b[0m\n{}\n\x1b[1;1mGithub:\x1b[0m\n{}\n",
+        local.join("\n"),
+        remote.join("\n")
+    )
+    .unwrap_or_default();
+    let _ = handle.flush();
 
-    pr_template += &"\n## 🧪 How to manually test this PR";
-    if pr_test_steps.is_some() {
-        pr_template += &"\n";
-        pr_template += &pr_test_steps.unwrap();
-        pr_template += &"\n";
-    } else {
-        pr_template += &"1.";
-        pr_template += &"\n";
+    let options = vec![
+        KEEP_LOCAL_OPTION,
+        KEEP_REMOTE_OPTION,
+        KEEP_BOTH_OPTION,
+        EDIT_OPTION,
+    ];
+    let both: Vec<String> = local.iter().chain(remote.iter()).cloned().collect();
+    match Select::new("Which version do you want to keep?", options).prompt() {
+        Ok(KEEP_LOCAL_OPTION) => local.to_vec(),
+        Ok(KEEP_REMOTE_OPTION) => remote.to_vec(),
+        Ok(KEEP_BOTH_OPTION) => both,
+        Ok(_) => match Editor::new("Edit the merged section")
+            .with_predefined_text(&both.join("\n"))
+            .prompt()
+        {
+            Ok(x) => x.lines().map(|l| l.to_owned()).collect(),
+            Err(_) => process::exit(1),
+        },
+        Err(_) => process::exit(1),
     }
+}
 
-    pr_template += "\n## Good PR check list\n";
-
-    let has_description_x = match has_description {
-        true => "x",
-        false => " ",
-    };
-    pr_template += &format!(
-        "- [{}] ✍️ I wrote an easy-to-read, short description at the top, with a good title\n",
-        has_description_x
-    )
-    .to_string();
-    pr_template += &format!(
-        "- [x] 🔗 I linked this PR to an issue (which is in progress): fixes #{}",
-        issue_id
-    )
-    .to_string();
-    pr_template += &"
-- [ ] 📋 I filled out the risk level, how to test, impact, what the PR does
-- [ ] 🏷️ I added the right labels. [api? BENApp? someOtherApp?]
-- [ ] 🥸 I assigned myself to the PR and others (as needed)
-- [ ] 🚀 I moved the PR into ready state - it's ready to be reviewed!
-- [ ] 🤖 I enabled auto merge"
-        .to_string();
-    info!("PR template:\n{}", pr_template);
-    return pr_template.to_owned();
+pub fn reviewers_prompt(suggested: Vec<String>) -> Vec<String> {
+    if suggested.is_empty() {
+        return vec![];
+    }
+    let reviewers_prompt = MultiSelect::new("Request reviews from the code owners?", suggested)
+        .with_all_selected_by_default()
+        .with_help_message("Together they cover every owned file in the branch")
+        .prompt();
+    match reviewers_prompt {
+        Ok(reviewers) => reviewers,
+        Err(_) => process::exit(1),
+    }
 }
@@ -0,0 +1,362 @@
+use chrono::{DateTime, Utc};
+use clap::ArgMatches;
+use log::{debug, info};
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    cmp::Reverse,
+    io::{self, Write},
+    process::Command,
+};
+
+use crate::{
+    storage, utils::extract_github_spaces_data::extract_github_spaces_data,
+    ux_utils::exit_with_error,
+};
+
+pub const TEXT_FORMAT: &str = "text";
+pub const JSON_FORMAT: &str = "json";
+pub const PRS_FORMATS: [&str; 2] = [TEXT_FORMAT, JSON_FORMAT];
+pub const PRS_SORTS: [&str; 5] = ["attention", "age", "updated", "repo", "comments"];
+pub const PRS_FILTERS: [&str; 10] = [
+    "attention",
+    "failing",
+    "pending",
+    "approved",
+    "changes-requested",
+    "review-required",
+    "conflicting",
+    "unresolved",
+    "draft",
+    "ready",
+];
+// Github rejects search queries longer than this
+const MAX_QUERY_LENGTH: usize = 256;
+const PAGE_SIZE: usize = 50;
+const MAX_PAGES: usize = 10;
+const SEARCH_QUERY: &str = "query($q: String!, $cursor: String) {
+  search(query: $q, type: ISSUE, first: 50, after: $cursor) {
+    pageInfo { hasNextPage endCursor }
+    nodes {
+      ... on PullRequest {
+        number title url isDraft createdAt updatedAt
+        repository { nameWithOwner }
+        reviewDecision mergeable
+        commits(last: 1) { nodes { commit { statusCheckRollup { state } } } }
+        reviewThreads(first: 100) { nodes { isResolved } }
+      }
+    }
+  }
+}";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PrStatus {
+    pub repository: String,
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    pub is_draft: bool,
+    // SUCCESS, FAILURE, ERROR, PENDING, EXPECTED or NONE when there are no checks
+    pub checks: String,
+    // APPROVED, CHANGES_REQUESTED, REVIEW_REQUIRED or NONE when reviews aren't required
+    pub review_decision: String,
+    pub unresolved_threads: usize,
+    // MERGEABLE, CONFLICTING or UNKNOWN while Github computes it
+    pub mergeable: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub age_days: i64,
+}
+
+impl PrStatus {
+    pub fn checks_failing(&self) -> bool {
+        self.checks == "FAILURE" || self.checks == "ERROR"
+    }
+
+    pub fn checks_pending(&self) -> bool {
+        self.checks == "PENDING" || self.checks == "EXPECTED"
+    }
+
+    // Something the author has to act on
+    pub fn needs_attention(&self) -> bool {
+        self.checks_failing()
+            || self.review_decision == "CHANGES_REQUESTED"
+            || self.unresolved_threads > 0
+            || self.mergeable == "CONFLICTING"
+    }
+
+    pub fn matches_filter(&self, filter: &str) -> bool {
+        match filter {
+            "attention" => self.needs_attention(),
+            "failing" => self.checks_failing(),
+            "pending" => self.checks_pending(),
+            "approved" => self.review_decision == "APPROVED",
+            "changes-requested" => self.review_decision == "CHANGES_REQUESTED",
+            "review-required" => self.review_decision == "REVIEW_REQUIRED",
+            "conflicting" => self.mergeable == "CONFLICTING",
+            "unresolved" => self.unresolved_threads > 0,
+            "draft" => self.is_draft,
+            "ready" => !self.is_draft,
+            _ => true,
+        }
+    }
+
+    pub fn line(&self) -> String {
+        let checks = match self.checks.as_str() {
+            _ if self.checks_failing() => "\x1b[1;31mchecks failing\x1b[0m".to_owned(),
+            _ if self.checks_pending() => "\x1b[1;33mchecks pending\x1b[0m".to_owned(),
+            "SUCCESS" => "\x1b[1;32mchecks passing\x1b[0m".to_owned(),
+            _ => "no checks".to_owned(),
+        };
+        let review = match self.review_decision.as_str() {
+            "APPROVED" => "\x1b[1;32mapproved\x1b[0m".to_owned(),
+            "CHANGES_REQUESTED" => "\x1b[1;31mchanges requested\x1b[0m".to_owned(),
+            "REVIEW_REQUIRED" => "\x1b[1;33mreview required\x1b[0m".to_owned(),
+            _ => "no review required".to_owned(),
+        };
+        let mergeable = match self.mergeable.as_str() {
+            "CONFLICTING" => "\x1b[1;31mconflicts\x1b[0m",
+            "MERGEABLE" => "mergeable",
+            _ => "mergeability unknown",
+        };
+        format!(
+            "{}\x1b[1;1m{}#{}\x1b[0m {}{}\n  {} · {} · {} unresolved · {} · {} days old\n  {}",
+            if self.needs_attention() {
+                "⚠️ "
+            } else {
+                ""
+            },
+            self.repository,
+            self.number,
+            self.title,
+            if self.is_draft { " (draft)" } else { "" },
+            checks,
+            review,
+            self.unresolved_threads,
+            mergeable,
+            self.age_days,
+            self.url
+        )
+    }
+}
+
+// Repositories are split over several queries to stay under the search length limit
+pub fn search_queries(repositories: &[String]) -> Vec<String> {
+    let base = "is:pr is:open author:@me archived:false";
+    let mut queries = vec![];
+    let mut query = base.to_owned();
+    for repository in repositories {
+        let qualifier = format!(" repo:{}", repository);
+        if query.len() + qualifier.len() > MAX_QUERY_LENGTH && query != base {
+            queries.push(query);
+            query = base.to_owned();
+        }
+        query.push_str(&qualifier);
+    }
+    if query != base {
+        queries.push(query);
+    }
+    queries
+}
+
+fn string_at(node: &Value, pointer: &str, default: &str) -> String {
+    node.pointer(pointer)
+        .and_then(|v| v.as_str())
+        .unwrap_or(default)
+        .to_owned()
+}
+
+pub fn parse_pr(node: &Value, now: DateTime<Utc>) -> Option<PrStatus> {
+    let number = node.get("number")?.as_u64()?;
+    let created_at = string_at(node, "/createdAt", "");
+    let age_days = DateTime::parse_from_rfc3339(&created_at)
+        .map(|created| (now - created.with_timezone(&Utc)).num_days())
+        .unwrap_or(0);
+    let unresolved_threads = node
+        .pointer("/reviewThreads/nodes")
+        .and_then(|n| n.as_array())
+        .map(|threads| {
+            threads
+                .iter()
+                .filter(|t| t.get("isResolved").and_then(|r| r.as_bool()) == Some(false))
+                .count()
+        })
+        .unwrap_or(0);
+    Some(PrStatus {
+        repository: string_at(node, "/repository/nameWithOwner", ""),
+        number,
+        title: string_at(node, "/title", ""),
+        url: string_at(node, "/url", ""),
+        is_draft: node
+            .get("isDraft")
+            .and_then(|d| d.as_bool())
+            .unwrap_or(false),
+        checks: string_at(
+            node,
+            "/commits/nodes/0/commit/statusCheckRollup/state",
+            "NONE",
+        ),
+        review_decision: string_at(node, "/reviewDecision", "NONE"),
+        unresolved_threads,
+        mergeable: string_at(node, "/mergeable", "UNKNOWN"),
+        created_at,
+        updated_at: string_at(node, "/updatedAt", ""),
+        age_days,
+    })
+}
+
+fn search_prs(query: &str, now: DateTime<Utc>) -> Result<Vec<PrStatus>, String> {
+    let mut prs = vec![];
+    let mut cursor: Option<String> = None;
+    for _ in 0..MAX_PAGES {
+        let mut command = Command::new("gh");
+        command
+            .arg("api")
+            .arg("graphql")
+            .arg("-f")
+            .arg(format!("query={}", SEARCH_QUERY))
+            .arg("-f")
+            .arg(format!("q={}", query));
+        if let Some(cursor) = &cursor {
+            command.arg("-f").arg(format!("cursor={}", cursor));
+        }
+        debug!("Searching PRs: {}", query);
+        let output = command.output().map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+        }
+        let response: Value = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+        let nodes = response
+            .pointer("/data/search/nodes")
+            .and_then(|n| n.as_array())
+            .cloned()
+            .unwrap_or_default();
+        prs.extend(nodes.iter().filter_map(|node| parse_pr(node, now)));
+        let has_next_page = response
+            .pointer("/data/search/pageInfo/hasNextPage")
+            .and_then(|h| h.as_bool())
+            .unwrap_or(false);
+        cursor = response
+            .pointer("/data/search/pageInfo/endCursor")
+            .and_then(|c| c.as_str())
+            .map(|c| c.to_owned());
+        if !has_next_page || nodes.len() < PAGE_SIZE || cursor.is_none() {
+            break;
+        }
+    }
+    Ok(prs)
+}
+
+pub fn sort_prs(prs: &mut [PrStatus], sort: &str) {
+    match sort {
+        "age" => prs.sort_by_key(|pr| Reverse(pr.age_days)),
+        "updated" => prs.sort_by(|a, b| b.updated_at.cmp(&a.updated_at)),
+        "repo" => prs.sort_by(|a, b| {
+            a.repository
+                .cmp(&b.repository)
+                .then(a.number.cmp(&b.number))
+        }),
+        "comments" => prs.sort_by_key(|pr| Reverse(pr.unresolved_threads)),
+        // PRs needing attention first, the oldest first within each group
+        _ => prs.sort_by_key(|pr| (Reverse(pr.needs_attention()), Reverse(pr.age_days))),
+    }
+}
+
+async fn cached_repositories(directory: &str, github_api_token: Option<&str>) -> Vec<String> {
+    let spaces = match storage::load_github_config(directory).ok().flatten() {
+        Some(spaces) => spaces,
+        None if github_api_token.is_some() => {
+            info!("No cached repositories, fetching them");
+            let client = Client::new();
+            match extract_github_spaces_data(directory, &client, github_api_token).await {
+                Ok(Some(spaces)) => spaces,
+                Ok(None) => vec![],
+                Err(e) => exit_with_error(&format!("Could not fetch the repositories: {}", e)),
+            }
+        }
+        None => exit_with_error(
+            "No cached repositories. Run `ticket spaces` or pass --github-api-token to fetch them.",
+        ),
+    };
+    spaces.into_iter().map(|s| s.full_name).collect()
+}
+
+pub async fn prs(
+    matches: ArgMatches<'_>,
+    directory: &str,
+    github_api_token: Option<&str>,
+    has_gh: bool,
+) {
+    if !has_gh {
+        exit_with_error("The Github CLI is required to list the PRs.");
+    }
+    let mut repositories = cached_repositories(directory, github_api_token).await;
+    if let Some(repos) = matches.values_of("repo") {
+        let selected: Vec<&str> = repos.collect();
+        repositories.retain(|r| {
+            selected
+                .iter()
+                .any(|s| r == s || r.rsplit('/').next() == Some(s))
+        });
+    }
+    if repositories.is_empty() {
+        exit_with_error("No repositories to search.");
+    }
+
+    let now = Utc::now();
+    let mut prs: Vec<PrStatus> = vec![];
+    for query in search_queries(&repositories) {
+        match search_prs(&query, now) {
+            Ok(x) => prs.extend(x),
+            Err(e) => exit_with_error(&format!("Could not search the PRs: {}", e)),
+        }
+    }
+    info!(
+        "Found {} open PRs in {} repositories",
+        prs.len(),
+        repositories.len()
+    );
+
+    let filters: Vec<&str> = matches
+        .values_of("filter")
+        .map(|f| f.collect())
+        .unwrap_or_default();
+    prs.retain(|pr| filters.iter().all(|f| pr.matches_filter(f)));
+    sort_prs(&mut prs, matches.value_of("sort").unwrap_or("attention"));
+    if matches.is_present("reverse") {
+        prs.reverse();
+    }
+
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    match matches.value_of("format").unwrap_or(TEXT_FORMAT) {
+        JSON_FORMAT => {
+            writeln!(
+                handle,
+                "{}",
+                serde_json::to_string_pretty(&prs).unwrap_or_default()
+            )
+            .unwrap_or_default();
+        }
+        _ => {
+            if prs.is_empty() {
+                writeln!(handle, "No open PRs found").unwrap_or_default();
+            }
+            for pr in &prs {
+                writeln!(handle, "{}", pr.line()).unwrap_or_default();
+            }
+            let attention = prs.iter().filter(|pr| pr.needs_attention()).count();
+            if attention > 0 {
+                writeln!(
+                    handle,
+                    "\n{} of {} PRs need attention",
+                    attention,
+                    prs.len()
+                )
+                .unwrap_or_default();
+            }
+        }
+    }
+    let _ = handle.flush();
+}
@@ -0,0 +1,385 @@
+use clap::ArgMatches;
+use inquire::Confirm;
+use lazy_static::lazy_static;
+use log::info;
+use regex::Regex;
+use reqwest::Client;
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{self, Write},
+    path::Path,
+    process,
+};
+
+use crate::{
+    branch_utils,
+    changelog::{self, ChangelogEntry},
+    commit_message::CommitHeader,
+    pr_metadata::shell_quote,
+    utils::{
+        extract_github_spaces_data::make_github_post,
+        git::{git_output, run_git},
+    },
+    ux_utils::exit_with_error,
+};
+
+lazy_static! {
+    static ref CARGO_VERSION_REGEX: Regex =
+        Regex::new(r#"(?m)^version\s*=\s*"(?P<version>[^"]+)""#).unwrap();
+    static ref CARGO_NAME_REGEX: Regex = Regex::new(r#"(?m)^name\s*=\s*"(?P<name>[^"]+)""#).unwrap();
+    static ref PACKAGE_JSON_VERSION_REGEX: Regex =
+        Regex::new(r#""version"\s*:\s*"(?P<version>[^"]+)""#).unwrap();
+}
+
+pub const BUMP_TYPES: [&str; 3] = ["major", "minor", "patch"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    pub fn parse(version: &str) -> Option<Version> {
+        let version = version.trim().trim_start_matches('v');
+        // Pre-release and build metadata are dropped
+        let core = version.split(['-', '+']).next()?;
+        let parts: Vec<u64> = core
+            .split('.')
+            .map(|p| p.parse().ok())
+            .collect::<Option<Vec<u64>>>()?;
+        match parts.as_slice() {
+            [major, minor, patch] => Some(Version {
+                major: *major,
+                minor: *minor,
+                patch: *patch,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn bump(&self, bump_type: &str) -> Version {
+        match bump_type {
+            "major" => Version {
+                major: self.major + 1,
+                minor: 0,
+                patch: 0,
+            },
+            "minor" => Version {
+                major: self.major,
+                minor: self.minor + 1,
+                patch: 0,
+            },
+            _ => Version {
+                major: self.major,
+                minor: self.minor,
+                patch: self.patch + 1,
+            },
+        }
+    }
+
+    pub fn format(&self) -> String {
+        format!("{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+// Breaking changes are a major bump, features a minor one and everything else a patch
+pub fn bump_type_for(entries: &[ChangelogEntry]) -> &'static str {
+    if entries.iter().any(|e| e.is_breaking()) {
+        "major"
+    } else if entries.iter().any(|e| e.header.commit_type == "feat") {
+        "minor"
+    } else {
+        "patch"
+    }
+}
+
+fn manifest_version(directory: &str) -> Option<Version> {
+    let cargo_version = fs::read_to_string(Path::new(directory).join("Cargo.toml"))
+        .ok()
+        .and_then(|c| {
+            CARGO_VERSION_REGEX
+                .captures(&c)
+                .map(|caps| caps["version"].to_owned())
+        });
+    let package_version = fs::read_to_string(Path::new(directory).join("package.json"))
+        .ok()
+        .and_then(|c| {
+            PACKAGE_JSON_VERSION_REGEX
+                .captures(&c)
+                .map(|caps| caps["version"].to_owned())
+        });
+    cargo_version
+        .or(package_version)
+        .and_then(|v| Version::parse(&v))
+}
+
+pub fn current_version(directory: &str, last_tag: Option<&str>) -> Version {
+    manifest_version(directory)
+        .or(last_tag.and_then(Version::parse))
+        .unwrap_or(Version {
+            major: 0,
+            minor: 0,
+            patch: 0,
+        })
+}
+
+fn replace_first(regex: &Regex, content: &str, group: &str, new_value: &str) -> Option<String> {
+    let caps = regex.captures(content)?;
+    let value = caps.name(group)?;
+    Some(format!(
+        "{}{}{}",
+        &content[..value.start()],
+        new_value,
+        &content[value.end()..]
+    ))
+}
+
+// Same files `scripts/bump_version.sh` keeps in sync. Returns the files that changed, or the
+// file that could not be written
+pub fn update_manifests(directory: &str, new_version: &str) -> Result<Vec<String>, String> {
+    let mut updated_files = vec![];
+
+    let cargo_toml_path = Path::new(directory).join("Cargo.toml");
+    if let Ok(cargo_toml) = fs::read_to_string(&cargo_toml_path) {
+        if let Some(updated) = replace_first(&CARGO_VERSION_REGEX, &cargo_toml, "version", new_version) {
+            fs::write(&cargo_toml_path, updated).map_err(|e| format!("Cargo.toml: {}", e))?;
+            updated_files.push("Cargo.toml".to_owned());
+        }
+
+        // Cargo.lock keeps the package version as well
+        let cargo_lock_path = Path::new(directory).join("Cargo.lock");
+        let package_name = CARGO_NAME_REGEX
+            .captures(&cargo_toml)
+            .map(|caps| caps["name"].to_owned());
+        if let (Some(name), Ok(cargo_lock)) = (package_name, fs::read_to_string(&cargo_lock_path)) {
+            let lock_regex = Regex::new(&format!(
+                r#"(?m)^name = "{}"\nversion = "(?P<version>[^"]+)""#,
+                regex::escape(&name)
+            ))
+            .unwrap();
+            if let Some(updated) = replace_first(&lock_regex, &cargo_lock, "version", new_version) {
+                fs::write(&cargo_lock_path, updated).map_err(|e| format!("Cargo.lock: {}", e))?;
+                updated_files.push("Cargo.lock".to_owned());
+            }
+        }
+    }
+
+    let package_json_path = Path::new(directory).join("package.json");
+    if let Ok(package_json) = fs::read_to_string(&package_json_path) {
+        if let Some(updated) =
+            replace_first(&PACKAGE_JSON_VERSION_REGEX, &package_json, "version", new_version)
+        {
+            fs::write(&package_json_path, updated)
+                .map_err(|e| format!("package.json: {}", e))?;
+            updated_files.push("package.json".to_owned());
+        }
+    }
+    info!("Updated version in {:?}", updated_files);
+    Ok(updated_files)
+}
+
+fn is_worktree_clean(directory: &str) -> bool {
+    git_output(directory, "status --porcelain --untracked-files=no")
+        .is_some_and(|status| status.is_empty())
+}
+
+fn confirm_prompt(message: &str, ci_mode: bool) -> bool {
+    if ci_mode {
+        return true;
+    }
+    match Confirm::new(message).with_default(true).prompt() {
+        Ok(x) => x,
+        Err(_) => process::exit(1),
+    }
+}
+
+fn quoted_files(files: &[String]) -> String {
+    files
+        .iter()
+        .map(|f| shell_quote(f))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+async fn create_github_release(
+    directory: &str,
+    tag: &str,
+    notes: &str,
+    github_api_token: Option<&str>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if github_api_token.is_none() {
+        return Err("A Github API token is required to create a release.".into());
+    }
+    let repo_parts = branch_utils::get_branch_origin_parts(directory)?;
+    let Some(owner_and_path) = repo_parts.owner_and_path else {
+        return Err("The origin remote is not a Github repository.".into());
+    };
+    let url = format!("https://api.github.com/repos/{}/releases", owner_and_path);
+    let mut body: HashMap<&str, &str> = HashMap::new();
+    body.insert("tag_name", tag);
+    body.insert("name", tag);
+    body.insert("body", notes);
+    let client = Client::new();
+    let response = make_github_post(&client, &url, github_api_token, body).await?;
+    Ok(response
+        .get("html_url")
+        .and_then(|u| u.as_str())
+        .unwrap_or("")
+        .to_owned())
+}
+
+pub async fn release(
+    matches: ArgMatches<'_>,
+    directory: &str,
+    ci_mode: bool,
+    no_verify: bool,
+    github_api_token: Option<&str>,
+) {
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    let dry_run = matches.is_present("dry_run");
+
+    if !dry_run && !is_worktree_clean(directory) {
+        exit_with_error("Commit or stash your changes before creating a release.");
+    }
+
+    let last_tag = changelog::last_tag(directory);
+    let range = match &last_tag {
+        Some(tag) => format!("{}..HEAD", tag),
+        None => "HEAD".to_owned(),
+    };
+    let entries = changelog::commits_in_range(directory, &range);
+    if entries.is_empty() && !matches.is_present("bump") {
+        writeln!(handle, "No commits to release since {}.", last_tag.unwrap_or("the start".to_owned()))
+            .unwrap_or_default();
+        let _ = handle.flush();
+        return;
+    }
+
+    let current = current_version(directory, last_tag.as_deref());
+    let bump_type = matches
+        .value_of("bump")
+        .unwrap_or_else(|| bump_type_for(&entries));
+    let new_version = current.bump(bump_type).format();
+    let tag = format!("v{}", new_version);
+
+    let config = changelog::changelog_config(directory);
+    let format = config
+        .format
+        .clone()
+        .unwrap_or(changelog::KEEP_A_CHANGELOG_FORMAT.to_owned());
+    let links = changelog::changelog_links(directory, &config);
+    let notes = changelog::render_section(&entries, Some(&new_version), &format, &links);
+
+    writeln!(
+        handle,
+        "\n\x1b[1;1mRelease {} -> {}\x1b[0m ({} bump, {} commits since {})\n\n{}",
+        current.format(),
+        new_version,
+        bump_type,
+        entries.len(),
+        last_tag.as_deref().unwrap_or("the start"),
+        notes
+    )
+    .unwrap_or_default();
+    let _ = handle.flush();
+    if dry_run || !confirm_prompt(&format!("Create release {}?", tag), ci_mode) {
+        return;
+    }
+
+    let mut changed_files = match update_manifests(directory, &new_version) {
+        Ok(files) => files,
+        Err(e) => exit_with_error(&format!("Could not update the version in {}", e)),
+    };
+    let changelog_path = config.path.clone().unwrap_or("CHANGELOG.md".to_owned());
+    let changelog_file = Path::new(directory).join(&changelog_path);
+    let updated_changelog =
+        changelog::update_changelog(fs::read_to_string(&changelog_file).ok(), &notes, &format);
+    if let Err(e) = fs::write(&changelog_file, updated_changelog) {
+        // The worktree was clean, so the manifests can go back to the committed version
+        if !changed_files.is_empty() {
+            run_git(directory, &format!("checkout -- {}", quoted_files(&changed_files)));
+        }
+        exit_with_error(&format!("Could not update {}: {}", changelog_path, e));
+    }
+    changed_files.push(changelog_path);
+
+    let release_message = CommitHeader::new("chore", Some("release".to_owned()), &tag, "", "").format();
+    let add_files = quoted_files(&changed_files);
+    let verify_flag = if no_verify { " --no-verify" } else { "" };
+    if !run_git(directory, &format!("add {}", add_files))
+        || !run_git(
+            directory,
+            &format!("commit{} -m '{}'", verify_flag, release_message),
+        )
+    {
+        exit_with_error("Could not commit the release changes.");
+    }
+
+    // The tag message is the changelog section, so `git show <tag>` has the release notes
+    let notes_path = env::temp_dir().join(format!("pull_requests_release_{}.md", process::id()));
+    if fs::write(&notes_path, &notes).is_err()
+        || !run_git(
+            directory,
+            &format!("tag -a {} --cleanup=verbatim -F '{}'", tag, notes_path.display()),
+        )
+    {
+        let _ = fs::remove_file(&notes_path);
+        exit_with_error(&format!("Could not create the tag {}.", tag));
+    }
+    let _ = fs::remove_file(&notes_path);
+    writeln!(handle, "\x1b[1;32mCreated {} and tag {}\x1b[0m", release_message, tag)
+        .unwrap_or_default();
+    let _ = handle.flush();
+
+    if !confirm_prompt("Push the release commit and tag?", ci_mode) {
+        return;
+    }
+    if !run_git(directory, &format!("push{}", verify_flag))
+        || !run_git(directory, &format!("push{} origin {}", verify_flag, tag))
+    {
+        exit_with_error("Could not push the release.");
+    }
+
+    if matches.is_present("github_release") {
+        match create_github_release(directory, &tag, &notes, github_api_token).await {
+            Ok(url) => {
+                writeln!(handle, "\x1b[1;32mCreated Github release\x1b[0m {}", url).unwrap_or_default();
+            }
+            Err(e) => {
+                writeln!(handle, "\x1b[1;31mCould not create the Github release:\x1b[0m {}", e)
+                    .unwrap_or_default();
+            }
+        }
+        let _ = handle.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(header: &str) -> ChangelogEntry {
+        ChangelogEntry {
+            hash: "abcdef1234567".to_owned(),
+            header: CommitHeader::parse(header).unwrap(),
+            pr_number: None,
+            breaking_description: None,
+        }
+    }
+
+    #[test]
+    fn breaking_changes_bump_the_major_version() {
+        assert_eq!(bump_type_for(&[entry("fix: a"), entry("feat!: b")]), "major");
+        assert_eq!(bump_type_for(&[entry("fix: a"), entry("feat: b")]), "minor");
+        assert_eq!(bump_type_for(&[entry("fix: a"), entry("chore: b")]), "patch");
+
+        let current = Version::parse("v0.4.2").unwrap();
+        assert_eq!(current.bump("major").format(), "1.0.0");
+        assert_eq!(current.bump("minor").format(), "0.5.0");
+        assert_eq!(current.bump("patch").format(), "0.4.3");
+    }
+}
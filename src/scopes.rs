@@ -0,0 +1,168 @@
+use lazy_static::lazy_static;
+use log::{debug, info};
+use regex::Regex;
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{
+    prompt_templates,
+    storage::{self, ScopeMapping},
+};
+
+lazy_static! {
+    static ref CARGO_WORKSPACE_REGEX: Regex =
+        Regex::new(r"(?s)\[workspace\][^\[]*?members\s*=\s*\[(.*?)\]").unwrap();
+    static ref QUOTED_REGEX: Regex = Regex::new(r#""([^"]+)""#).unwrap();
+}
+
+// Expands `packages/*` style entries into the matching directories
+fn expand_workspace_entry(directory: &str, entry: &str) -> Vec<String> {
+    let trimmed = entry.trim_end_matches('/');
+    let Some(prefix) = trimmed
+        .strip_suffix("/**")
+        .or_else(|| trimmed.strip_suffix("/*"))
+    else {
+        return vec![trimmed.to_owned()];
+    };
+
+    let mut members = vec![];
+    if let Ok(entries) = fs::read_dir(Path::new(directory).join(prefix)) {
+        for entry in entries.flatten() {
+            if entry.path().is_dir() {
+                if let Some(name) = entry.file_name().to_str() {
+                    members.push(format!("{}/{}", prefix, name));
+                }
+            }
+        }
+    }
+    members.sort();
+    members
+}
+
+fn cargo_workspace_members(directory: &str) -> Vec<String> {
+    let Ok(cargo_toml) = fs::read_to_string(Path::new(directory).join("Cargo.toml")) else {
+        return vec![];
+    };
+    let Some(caps) = CARGO_WORKSPACE_REGEX.captures(&cargo_toml) else {
+        return vec![];
+    };
+    QUOTED_REGEX
+        .captures_iter(caps.get(1).unwrap().as_str())
+        .flat_map(|c| expand_workspace_entry(directory, c.get(1).unwrap().as_str()))
+        .collect()
+}
+
+fn npm_workspace_members(directory: &str) -> Vec<String> {
+    let Ok(package_json) = fs::read_to_string(Path::new(directory).join("package.json")) else {
+        return vec![];
+    };
+    let Ok(package) = serde_json::from_str::<serde_json::Value>(&package_json) else {
+        return vec![];
+    };
+    // Both `"workspaces": []` and `"workspaces": { "packages": [] }` are valid
+    let workspaces = match package.get("workspaces") {
+        Some(serde_json::Value::Array(x)) => x.clone(),
+        Some(serde_json::Value::Object(x)) => x
+            .get("packages")
+            .and_then(|p| p.as_array())
+            .cloned()
+            .unwrap_or_default(),
+        _ => vec![],
+    };
+    workspaces
+        .iter()
+        .filter_map(|w| w.as_str())
+        .flat_map(|w| expand_workspace_entry(directory, w))
+        .collect()
+}
+
+pub fn workspace_scopes(directory: &str) -> Vec<ScopeMapping> {
+    let mut members = cargo_workspace_members(directory);
+    members.extend(npm_workspace_members(directory));
+    members
+        .into_iter()
+        .filter(|m| !m.is_empty() && m != ".")
+        .map(|member| ScopeMapping {
+            scope: member.rsplit('/').next().unwrap_or(&member).to_owned(),
+            paths: vec![member],
+            description: None,
+        })
+        .collect()
+}
+
+pub fn configured_scopes(directory: &str) -> Vec<ScopeMapping> {
+    let repo_config = storage::load_repo_config(directory);
+    let mut scopes = repo_config.scopes.unwrap_or_default();
+    if repo_config.detect_workspace_scopes.unwrap_or(true) {
+        for workspace_scope in workspace_scopes(directory) {
+            if !scopes.iter().any(|s| s.scope == workspace_scope.scope) {
+                scopes.push(workspace_scope);
+            }
+        }
+    }
+    debug!("Configured scopes: {:?}", scopes);
+    scopes
+}
+
+pub fn scope_names(scopes: &[ScopeMapping]) -> Vec<String> {
+    scopes.iter().map(|s| s.scope.clone()).collect()
+}
+
+fn matches_path(mapping_path: &str, file: &str) -> bool {
+    let prefix = mapping_path.trim_end_matches('/');
+    file == prefix || file.starts_with(&format!("{}/", prefix))
+}
+
+// Scopes ordered by how many of the paths they own, using the most specific mapping per path
+pub fn infer_scopes(scopes: &[ScopeMapping], paths: &[String]) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for file in paths {
+        if file.is_empty() {
+            continue;
+        }
+        let best_match = scopes
+            .iter()
+            .flat_map(|s| s.paths.iter().map(move |p| (s, p)))
+            .filter(|(_, p)| matches_path(p, file))
+            .max_by_key(|(_, p)| p.len());
+        if let Some((scope, _)) = best_match {
+            *counts.entry(scope.scope.clone()).or_insert(0) += 1;
+        }
+    }
+    let mut sorted_scopes: Vec<(String, usize)> = counts.into_iter().collect();
+    sorted_scopes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    info!("Inferred scopes: {:?}", sorted_scopes);
+    sorted_scopes.into_iter().map(|(scope, _)| scope).collect()
+}
+
+// Text for the `{{scopes}}` prompt template variable
+pub fn scopes_prompt_variable(scopes: &[ScopeMapping]) -> String {
+    if scopes.is_empty() {
+        return prompt_templates::DEFAULT_SCOPES.to_owned();
+    }
+    scopes
+        .iter()
+        .map(|s| {
+            let description = s.description.clone().unwrap_or(format!(
+                "files in {}",
+                s.paths.join(", ")
+            ));
+            format!("{}: {}", s.scope, description)
+        })
+        .collect::<Vec<String>>()
+        .join(" - ")
+}
+
+pub fn add_branch_scopes(git_branch: &str, directory: &str, new_scopes: &[String]) {
+    if new_scopes.is_empty() {
+        return;
+    }
+    let _ = storage::update_branch_config(git_branch, directory, |config| {
+        let mut scopes = config.scopes.clone().unwrap_or_default();
+        for scope in new_scopes {
+            if !scopes.contains(scope) {
+                scopes.push(scope.clone());
+            }
+        }
+        config.scopes = Some(scopes);
+    });
+}
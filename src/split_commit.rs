@@ -0,0 +1,318 @@
+use inquire::{Confirm, MultiSelect, Select};
+use log::info;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::{
+    branch_utils,
+    commit_message::{self, CommitHeader},
+    pr_metadata::shell_quote,
+    prompts, scopes,
+    storage::ScopeMapping,
+    utils::git::{git_output, run_git},
+};
+
+pub struct CommitGroup {
+    pub category: String,
+    pub files: Vec<String>,
+}
+
+pub struct SplitContext<'a> {
+    pub directory: &'a str,
+    pub git_branch: &'a str,
+    pub is_new_branch: bool,
+    pub issue_labels: &'a [String],
+    pub message_name: &'a str,
+    pub team_prefix: &'a str,
+    pub issue_id: &'a str,
+    pub no_verify: bool,
+}
+
+pub struct PlannedCommit {
+    pub files: Vec<String>,
+    pub commit_message: String,
+    pub scopes: Vec<String>,
+}
+
+// Deleted sides of renames have to be staged together with the new path, so renames are split
+fn staged_files_without_renames(directory: &str) -> Vec<String> {
+    git_output(directory, "diff --cached --name-only --no-renames")
+        .unwrap_or_default()
+        .lines()
+        .map(|l| l.trim().to_owned())
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+pub fn group_staged_files(directory: &str) -> Vec<CommitGroup> {
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for file in staged_files_without_renames(directory) {
+        let category = branch_utils::classify_file(&file).to_owned();
+        groups.entry(category).or_default().push(file);
+    }
+    groups
+        .into_iter()
+        .map(|(category, files)| CommitGroup { category, files })
+        .collect()
+}
+
+fn print_groups(groups: &[CommitGroup]) {
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    let mut output_text = "\n\x1b[1;1mStaged changes by type\x1b[0m\n".to_owned();
+    for group in groups {
+        output_text.push_str(&format!(
+            "- \x1b[1;1m{}\x1b[0m ({} files)\n",
+            group.category,
+            group.files.len()
+        ));
+        for file in &group.files {
+            output_text.push_str(&format!("    {}\n", file));
+        }
+    }
+    writeln!(handle, "{}", output_text).unwrap_or_default();
+    let _ = handle.flush();
+}
+
+fn reassign_files(groups: &mut Vec<CommitGroup>) {
+    loop {
+        print_groups(groups);
+        let should_move = Confirm::new("Do you want to move files between commits?")
+            .with_default(false)
+            .prompt();
+        match should_move {
+            Ok(false) => break,
+            Ok(true) => {}
+            Err(_) => process::exit(1),
+        }
+
+        let all_files: Vec<String> = groups.iter().flat_map(|g| g.files.clone()).collect();
+        let selected_files = match MultiSelect::new("Select the files to move", all_files).prompt()
+        {
+            Ok(x) => x,
+            Err(_) => process::exit(1),
+        };
+        if selected_files.is_empty() {
+            continue;
+        }
+
+        let mut target_options: Vec<String> = groups.iter().map(|g| g.category.clone()).collect();
+        for type_option in prompts::TYPE_OPTIONS.iter() {
+            let short_type = type_option.split(':').next().unwrap_or("").to_owned();
+            if !target_options.contains(&short_type) {
+                target_options.push(short_type);
+            }
+        }
+        let target = match Select::new("Move them to", target_options).prompt() {
+            Ok(x) => x,
+            Err(_) => process::exit(1),
+        };
+
+        for group in groups.iter_mut() {
+            group.files.retain(|f| !selected_files.contains(f));
+        }
+        match groups.iter_mut().find(|g| g.category == target) {
+            Some(group) => group.files.extend(selected_files),
+            None => groups.push(CommitGroup {
+                category: target,
+                files: selected_files,
+            }),
+        }
+        groups.retain(|g| !g.files.is_empty());
+    }
+}
+
+fn plan_commit(
+    group: &CommitGroup,
+    staged_changes: &[branch_utils::FileChange],
+    configured_scopes: &[ScopeMapping],
+    context: &SplitContext,
+) -> PlannedCommit {
+    let proposed_type = match group.category.as_str() {
+        // Source files keep the classifier rules, other categories are already a type
+        "code" => {
+            let changes: Vec<branch_utils::FileChange> = staged_changes
+                .iter()
+                .filter(|change| group.files.contains(&change.path))
+                .cloned()
+                .collect();
+            branch_utils::suggest_type(&changes, context.is_new_branch, context.issue_labels)
+                .commit_type
+        }
+        category => Some(category.to_owned()),
+    };
+    let proposed_scope = scopes::infer_scopes(configured_scopes, &group.files)
+        .first()
+        .cloned();
+
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    writeln!(
+        handle,
+        "\n\x1b[1;1mCommit for {} ({} files)\x1b[0m",
+        group.category,
+        group.files.len()
+    )
+    .unwrap_or_default();
+    let _ = handle.flush();
+    let selected_type = prompts::select_types_prompt(proposed_type);
+    let selected_scope =
+        prompts::select_scope_prompt(scopes::scope_names(configured_scopes), proposed_scope);
+    let message = prompts::select_message_prompt(context.message_name, vec![]);
+
+    PlannedCommit {
+        files: group.files.clone(),
+        commit_message: CommitHeader::new(
+            &selected_type,
+            selected_scope.clone(),
+            &message,
+            context.team_prefix,
+            context.issue_id,
+        )
+        .format(),
+        scopes: selected_scope.into_iter().collect(),
+    }
+}
+
+// Keeping the staged patch (instead of re-adding files) preserves partially staged files
+fn staged_patch(directory: &str) -> Option<PathBuf> {
+    let patch_path = env::temp_dir().join(format!("pull_requests_split_{}.patch", process::id()));
+    let args = format!(
+        "diff --cached --binary --no-renames > {}",
+        shell_quote(&patch_path.display().to_string())
+    );
+    match run_git(directory, &args) {
+        true => Some(patch_path),
+        false => None,
+    }
+}
+
+fn git_stage(directory: &str, patch_path: &Path, files: &[String]) -> bool {
+    let includes: Vec<String> = files
+        .iter()
+        .map(|f| format!("--include={}", shell_quote(f)))
+        .collect();
+    let args = format!(
+        "apply --cached {} {}",
+        includes.join(" "),
+        shell_quote(&patch_path.display().to_string())
+    );
+    run_git(directory, &args)
+}
+
+fn git_unstage_all(directory: &str) -> bool {
+    run_git(directory, "reset -q")
+}
+
+// Splitting needs the type, scope and message prompts, so it is never offered in CI
+pub fn should_split_prompt(used_types: usize, ci_mode: bool) -> bool {
+    if used_types < 2 || ci_mode {
+        return false;
+    }
+    let split_prompt = Confirm::new(&format!(
+        "The staged changes span {} types. Do you want to split them into multiple commits?",
+        used_types
+    ))
+    .with_default(false)
+    .prompt();
+    match split_prompt {
+        Ok(x) => x,
+        Err(_) => process::exit(1),
+    }
+}
+
+// Returns the last commit message, or exits if any of the commits failed
+pub fn split_and_commit(context: &SplitContext, pr_template: Option<String>) -> Option<String> {
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    let directory = context.directory;
+    let configured_scopes = scopes::configured_scopes(directory);
+
+    let staged_changes = branch_utils::staged_file_changes(directory);
+    let mut groups = group_staged_files(directory);
+    reassign_files(&mut groups);
+
+    let planned_commits: Vec<PlannedCommit> = groups
+        .iter()
+        .map(|group| plan_commit(group, &staged_changes, &configured_scopes, context))
+        .collect();
+
+    let mut output_text = "\n\x1b[1;1mThe following commits will be created\x1b[0m\n".to_owned();
+    for planned_commit in &planned_commits {
+        output_text.push_str(&format!(
+            "- \x1b[1;32m{}\x1b[1;0m ({} files)\n",
+            planned_commit.commit_message,
+            planned_commit.files.len()
+        ));
+    }
+    writeln!(handle, "{}", output_text).unwrap_or_default();
+    let _ = handle.flush();
+    if !prompts::commit_pr_prompt(None) {
+        return None;
+    }
+
+    let Some(patch_path) = staged_patch(directory) else {
+        writeln!(handle, "\x1b[1;31mCould not save the staged changes.\x1b[0m").unwrap_or_default();
+        let _ = handle.flush();
+        process::exit(1);
+    };
+    if !git_unstage_all(directory) {
+        writeln!(handle, "\x1b[1;31mCould not unstage the changes.\x1b[0m").unwrap_or_default();
+        let _ = handle.flush();
+        process::exit(1);
+    }
+
+    // Only the trailers that don't need input are added to split commits
+    let footers = commit_message::build_footers(
+        directory,
+        context.git_branch,
+        context.issue_id,
+        None,
+        false,
+    );
+    let mut footers_message: Vec<String> = vec![];
+    if !footers.lines.is_empty() {
+        footers_message.push(footers.lines.join("\n"));
+    }
+    let mut last_commit_message = None;
+    for (index, planned_commit) in planned_commits.iter().enumerate() {
+        info!("Committing {}", planned_commit.commit_message);
+        let commit_result = match git_stage(directory, &patch_path, &planned_commit.files) {
+            true => branch_utils::commit_pr(
+                directory,
+                &planned_commit.commit_message,
+                footers_message.clone(),
+                context.git_branch,
+                pr_template.clone(),
+                context.no_verify,
+            ),
+            false => Ok(None),
+        };
+
+        if !matches!(commit_result, Ok(Some(0))) {
+            // Put back what wasn't committed so nothing is lost
+            git_unstage_all(directory);
+            for remaining_commit in &planned_commits[index..] {
+                git_stage(directory, &patch_path, &remaining_commit.files);
+            }
+            writeln!(
+                handle,
+                "\n\x1b[1;31mCommit failed for {}. The remaining changes were staged again.\x1b[1;0m\n",
+                planned_commit.commit_message
+            )
+            .unwrap_or_default();
+            let _ = handle.flush();
+            process::exit(1);
+        }
+        scopes::add_branch_scopes(context.git_branch, directory, &planned_commit.scopes);
+        last_commit_message = Some(planned_commit.commit_message.clone());
+    }
+
+    let _ = fs::remove_file(&patch_path);
+    last_commit_message
+}
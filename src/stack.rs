@@ -0,0 +1,570 @@
+use clap::ArgMatches;
+use log::{debug, info};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+    process::Command,
+};
+
+use crate::{
+    branch_utils,
+    pr_metadata::shell_quote,
+    storage,
+    types::github_types::GithubPullRequest,
+    utils::git::{git_output, run_git_inherit},
+    ux_utils::exit_with_error,
+};
+
+// The navigation table is kept between these markers so it can be replaced on every update
+pub const STACK_START_MARKER: &str = "<!-- stack -->";
+pub const STACK_END_MARKER: &str = "<!-- /stack -->";
+pub const MERGED_STATE: &str = "MERGED";
+pub const OPEN_STATE: &str = "OPEN";
+
+#[derive(Debug, Clone)]
+pub struct StackBranch {
+    pub name: String,
+    pub parent: String,
+    pub depth: usize,
+}
+
+fn is_ancestor(directory: &str, ancestor: &str, descendant: &str) -> bool {
+    git_output(
+        directory,
+        &format!(
+            "merge-base --is-ancestor {} {}",
+            shell_quote(ancestor),
+            shell_quote(descendant)
+        ),
+    )
+    .is_some()
+}
+
+pub fn parent_branch(directory: &str, git_branch: &str) -> Option<String> {
+    storage::get_branch_config(git_branch, directory)
+        .ok()
+        .flatten()
+        .and_then(|c| c.parent_branch)
+}
+
+// Progress snapshots and prompt overrides are kept next to the branch configs
+const NON_BRANCH_DIRECTORIES: [&str; 2] = ["progress", "prompts"];
+
+// Branch configs are saved as `.commit_message/<branch>.yaml`, so `feature/foo` is in a
+// subdirectory and its name is rebuilt from the relative path
+fn branch_config_names(path: &Path, prefix: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(path) else {
+        return vec![];
+    };
+    let mut names = vec![];
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if entry.path().is_dir() {
+            if prefix.is_empty() && NON_BRANCH_DIRECTORIES.contains(&file_name.as_str()) {
+                continue;
+            }
+            names.extend(branch_config_names(
+                &entry.path(),
+                &format!("{}{}/", prefix, file_name),
+            ));
+        } else if let Some(branch) = file_name.strip_suffix(".yaml") {
+            names.push(format!("{}{}", prefix, branch));
+        }
+    }
+    names
+}
+
+// (branch, parent) for every branch config in `.commit_message/` with a parent
+pub fn stack_links(directory: &str) -> Vec<(String, String)> {
+    let mut links: Vec<(String, String)> =
+        branch_config_names(&Path::new(directory).join(".commit_message"), "")
+            .into_iter()
+            .filter(|branch| branch != "config")
+            .filter_map(|branch| {
+                let parent = parent_branch(directory, &branch)?;
+                Some((branch, parent))
+            })
+            .collect();
+    links.sort();
+    links
+}
+
+// Branches stacked on `root`, parents before their children
+pub fn stack_branches(links: &[(String, String)], root: &str) -> Vec<StackBranch> {
+    let mut branches = vec![];
+    let mut pending: Vec<(String, usize)> = vec![(root.to_owned(), 0)];
+    while let Some((parent, depth)) = pending.pop() {
+        if depth > links.len() {
+            break;
+        }
+        let children: Vec<&String> = links
+            .iter()
+            .filter(|(_, p)| *p == parent)
+            .map(|(branch, _)| branch)
+            .collect();
+        for child in children.iter().rev() {
+            pending.push((child.to_string(), depth + 1));
+        }
+        if parent != root {
+            branches.push(StackBranch {
+                name: parent.clone(),
+                parent: links
+                    .iter()
+                    .find(|(branch, _)| *branch == parent)
+                    .map(|(_, p)| p.clone())
+                    .unwrap_or_default(),
+                depth: depth - 1,
+            });
+        }
+    }
+    branches
+}
+
+// The stack `git_branch` belongs to and the branch it's based on. Other stacks on the
+// default branch are left out
+pub fn current_stack(directory: &str, git_branch: &str) -> (String, Vec<StackBranch>) {
+    let links = stack_links(directory);
+    let default_branch = branch_utils::default_branch(directory);
+    let mut bottom = git_branch.to_owned();
+    // Bounded so a cycle in hand edited configs can't loop forever
+    for _ in 0..=links.len() {
+        match links.iter().find(|(branch, _)| *branch == bottom) {
+            Some((_, parent)) if *parent != default_branch => bottom = parent.clone(),
+            _ => break,
+        }
+    }
+    if !links
+        .iter()
+        .any(|(branch, parent)| *branch == bottom || *parent == bottom)
+    {
+        return (default_branch, vec![]);
+    }
+    let root = links
+        .iter()
+        .find(|(branch, _)| *branch == bottom)
+        .map(|(_, parent)| parent.clone())
+        .unwrap_or(default_branch);
+    let mut stack = vec![StackBranch {
+        name: bottom.clone(),
+        parent: root.clone(),
+        depth: 0,
+    }];
+    stack.extend(
+        stack_branches(&links, &bottom)
+            .into_iter()
+            .map(|branch| StackBranch {
+                depth: branch.depth + 1,
+                ..branch
+            }),
+    );
+    (root, stack)
+}
+
+pub fn branch_pr(directory: &str, branch: &str) -> Option<GithubPullRequest> {
+    let cmd_arg = format!(
+        "cd {} && gh pr view {} --json number,state,baseRefName,url,body",
+        shell_quote(directory),
+        shell_quote(branch)
+    );
+    let output = Command::new("sh").arg("-c").arg(cmd_arg).output().ok()?;
+    if !output.status.success() {
+        debug!(
+            "No PR for {}: {}",
+            branch,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return None;
+    }
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+fn stack_prs(directory: &str, stack: &[StackBranch]) -> HashMap<String, GithubPullRequest> {
+    stack
+        .iter()
+        .filter_map(|b| branch_pr(directory, &b.name).map(|pr| (b.name.clone(), pr)))
+        .collect()
+}
+
+fn pr_label(pr: Option<&GithubPullRequest>) -> String {
+    match pr {
+        Some(pr) => match (pr.number, pr.state.as_deref()) {
+            (Some(number), Some(MERGED_STATE)) => format!("#{} (merged)", number),
+            (Some(number), _) => format!("#{}", number),
+            _ => "-".to_owned(),
+        },
+        None => "-".to_owned(),
+    }
+}
+
+pub fn render_stack_table(
+    root: &str,
+    stack: &[StackBranch],
+    prs: &HashMap<String, GithubPullRequest>,
+    current: &str,
+) -> String {
+    let mut lines = vec![
+        STACK_START_MARKER.to_owned(),
+        "#### Stack".to_owned(),
+        "".to_owned(),
+        format!(
+            "Stacked on `{}`, merge from the top of the table down.",
+            root
+        ),
+        "".to_owned(),
+        "| | PR | Branch | Base |".to_owned(),
+        "| --- | --- | --- | --- |".to_owned(),
+    ];
+    for branch in stack {
+        lines.push(format!(
+            "| {} | {} | `{}` | `{}` |",
+            if branch.name == current { "→" } else { "" },
+            pr_label(prs.get(&branch.name)),
+            branch.name,
+            branch.parent
+        ));
+    }
+    lines.push(STACK_END_MARKER.to_owned());
+    lines.join("\n")
+}
+
+pub fn strip_stack_section(body: &str) -> String {
+    let (Some(start), Some(end)) = (body.find(STACK_START_MARKER), body.find(STACK_END_MARKER))
+    else {
+        return body.to_owned();
+    };
+    if end < start {
+        return body.to_owned();
+    }
+    let before = body[..start].trim_end();
+    let after = body[end + STACK_END_MARKER.len()..].trim_start();
+    match (before.is_empty(), after.is_empty()) {
+        (true, _) => after.to_owned(),
+        (_, true) => before.to_owned(),
+        _ => format!("{}\n\n{}", before, after),
+    }
+}
+
+pub fn with_stack_section(body: &str, table: Option<&str>) -> String {
+    let body = strip_stack_section(body);
+    match table {
+        Some(table) if body.trim().is_empty() => table.to_owned(),
+        Some(table) => format!("{}\n\n{}", body.trim_end(), table),
+        None => body,
+    }
+}
+
+// The navigation table for the PR of `git_branch`, None when the branch isn't stacked
+pub fn stack_section(directory: &str, git_branch: &str) -> Option<String> {
+    let (root, stack) = current_stack(directory, git_branch);
+    if stack.len() < 2 || !stack.iter().any(|b| b.name == git_branch) {
+        return None;
+    }
+    let prs = stack_prs(directory, &stack);
+    Some(render_stack_table(&root, &stack, &prs, git_branch))
+}
+
+fn create_stack_branch(directory: &str, git_branch: &str, new_branch: &str) {
+    branch_utils::validate_branch(directory, git_branch);
+    let Some(parent_head) = git_output(directory, "rev-parse HEAD") else {
+        exit_with_error("Could not read the current commit.");
+    };
+    if !run_git_inherit(
+        directory,
+        &format!("checkout -b {}", shell_quote(new_branch)),
+    ) {
+        exit_with_error(&format!("Could not create the branch {}.", new_branch));
+    }
+    let parent_config = storage::get_branch_config(git_branch, directory)
+        .ok()
+        .flatten();
+    let _ = storage::update_branch_config(new_branch, directory, |config| {
+        config.parent_branch = Some(git_branch.to_owned());
+        config.parent_head = Some(parent_head);
+        // The stack usually works on the same issue
+        if let Some(parent_config) = parent_config {
+            config.issue_id = parent_config.issue_id;
+            config.issue_name = parent_config.issue_name;
+            config.issue_description = parent_config.issue_description;
+        }
+    });
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    writeln!(
+        handle,
+        "Created \x1b[1;1m{}\x1b[0m on top of {}. Its PR will target {}.",
+        new_branch, git_branch, git_branch
+    )
+    .unwrap_or_default();
+    let _ = handle.flush();
+}
+
+fn show_stack(directory: &str, git_branch: &str) {
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    let (root, stack) = current_stack(directory, git_branch);
+    if stack.is_empty() {
+        writeln!(handle, "{} is not part of a stack.", git_branch).unwrap_or_default();
+        let _ = handle.flush();
+        return;
+    }
+    let prs = stack_prs(directory, &stack);
+    writeln!(handle, "{}", root).unwrap_or_default();
+    for branch in &stack {
+        writeln!(
+            handle,
+            "{}└ {}{} {}{}",
+            "  ".repeat(branch.depth),
+            if branch.name == git_branch {
+                "\x1b[1;1m"
+            } else {
+                ""
+            },
+            branch.name,
+            pr_label(prs.get(&branch.name)),
+            if branch.name == git_branch {
+                "\x1b[0m"
+            } else {
+                ""
+            },
+        )
+        .unwrap_or_default();
+    }
+    let _ = handle.flush();
+}
+
+// Refreshes the navigation table of every open PR in the stack
+pub fn sync_stack(directory: &str, git_branch: &str) {
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    let (root, stack) = current_stack(directory, git_branch);
+    let prs = stack_prs(directory, &stack);
+    for branch in &stack {
+        let Some(pr) = prs.get(&branch.name) else {
+            continue;
+        };
+        if pr.state.as_deref() != Some(OPEN_STATE) {
+            continue;
+        }
+        let body = pr.body.clone().unwrap_or_default();
+        let table = render_stack_table(&root, &stack, &prs, &branch.name);
+        let new_body = with_stack_section(&body, Some(&table));
+        if new_body == body {
+            continue;
+        }
+        let cmd_arg = format!(
+            "cd {} && gh pr edit {} --body {}",
+            shell_quote(directory),
+            shell_quote(&branch.name),
+            shell_quote(&new_body)
+        );
+        info!("Executing command: {}", cmd_arg);
+        match Command::new("sh").arg("-c").arg(cmd_arg).output() {
+            Ok(output) if output.status.success() => {
+                writeln!(handle, "Updated the stack table of {}", pr_label(Some(pr)))
+                    .unwrap_or_default();
+            }
+            _ => {
+                writeln!(
+                    handle,
+                    "\x1b[1;33mCould not update the PR of {}\x1b[0m",
+                    branch.name
+                )
+                .unwrap_or_default();
+            }
+        }
+    }
+    let _ = handle.flush();
+}
+
+// Replays every branch of the stack on its parent. Children of merged branches move to
+// the branch the merged one was stacked on
+pub fn rebase_stack(directory: &str, git_branch: &str, push: bool) {
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    if git_output(directory, "status --porcelain --untracked-files=no")
+        .is_none_or(|status| !status.is_empty())
+    {
+        exit_with_error("Commit or stash your changes before rebasing the stack.");
+    }
+    let (root, stack) = current_stack(directory, git_branch);
+    if stack.is_empty() {
+        writeln!(handle, "{} is not part of a stack.", git_branch).unwrap_or_default();
+        let _ = handle.flush();
+        return;
+    }
+    let _ = git_output(directory, "fetch origin");
+
+    // Merged branch -> branch its children are moved to
+    let mut merged: HashMap<String, String> = HashMap::new();
+    for branch in &stack {
+        let mut parent = branch.parent.clone();
+        while let Some(new_parent) = merged.get(&parent) {
+            parent = new_parent.clone();
+        }
+        let pr = branch_pr(directory, &branch.name);
+        if pr.as_ref().and_then(|p| p.state.as_deref()) == Some(MERGED_STATE) {
+            writeln!(
+                handle,
+                "{} was merged, moving the branches on top of it to {}",
+                branch.name, parent
+            )
+            .unwrap_or_default();
+            let _ = handle.flush();
+            merged.insert(branch.name.clone(), parent);
+            continue;
+        }
+
+        let onto = match parent == root {
+            true => branch_utils::base_ref(directory, &root),
+            false => parent.clone(),
+        };
+        let Some(onto_head) = git_output(directory, &format!("rev-parse {}", shell_quote(&onto)))
+        else {
+            exit_with_error(&format!("Could not find the branch {}.", onto));
+        };
+        if is_ancestor(directory, &onto, &branch.name) {
+            writeln!(handle, "{} is up to date with {}", branch.name, onto).unwrap_or_default();
+            let _ = handle.flush();
+        } else {
+            // Only the commits made on top of the parent are replayed, which also works
+            // when the parent was rewritten or squash merged
+            let old_base = storage::get_branch_config(&branch.name, directory)
+                .ok()
+                .flatten()
+                .and_then(|c| c.parent_head)
+                .filter(|head| is_ancestor(directory, head, &branch.name))
+                .or(git_output(
+                    directory,
+                    &format!(
+                        "merge-base {} {}",
+                        shell_quote(&branch.parent),
+                        shell_quote(&branch.name)
+                    ),
+                ))
+                .unwrap_or(onto_head.clone());
+            writeln!(handle, "Rebasing {} onto {}", branch.name, onto).unwrap_or_default();
+            let _ = handle.flush();
+            if !run_git_inherit(
+                directory,
+                &format!(
+                    "rebase --onto {} {} {}",
+                    shell_quote(&onto),
+                    shell_quote(&old_base),
+                    shell_quote(&branch.name)
+                ),
+            ) {
+                exit_with_error(&format!(
+                    "Rebasing {} stopped on conflicts. Resolve them, run `git rebase --continue` and rebase the stack again.",
+                    branch.name
+                ));
+            }
+            if push
+                && !run_git_inherit(
+                    directory,
+                    &format!(
+                        "push --force-with-lease origin {}",
+                        shell_quote(&branch.name)
+                    ),
+                )
+            {
+                writeln!(handle, "\x1b[1;33mCould not push {}\x1b[0m", branch.name)
+                    .unwrap_or_default();
+                let _ = handle.flush();
+            }
+        }
+
+        let _ = storage::update_branch_config(&branch.name, directory, |config| {
+            config.parent_branch = Some(parent.clone());
+            config.parent_head = Some(onto_head);
+        });
+        if parent != branch.parent && pr.is_some() {
+            let cmd_arg = format!(
+                "cd {} && gh pr edit {} --base {}",
+                shell_quote(directory),
+                shell_quote(&branch.name),
+                shell_quote(&parent)
+            );
+            info!("Executing command: {}", cmd_arg);
+            let _ = Command::new("sh").arg("-c").arg(cmd_arg).output();
+        }
+    }
+    let _ = git_output(directory, &format!("checkout {}", shell_quote(git_branch)));
+    if push {
+        sync_stack(directory, git_branch);
+    }
+}
+
+pub fn stack(matches: ArgMatches<'static>, directory: &str, git_branch: &str) {
+    match matches.subcommand() {
+        ("branch", Some(arg)) => {
+            create_stack_branch(directory, git_branch, arg.value_of("name").unwrap_or(""))
+        }
+        ("rebase", Some(arg)) => rebase_stack(directory, git_branch, arg.is_present("push")),
+        ("sync", Some(_)) => sync_stack(directory, git_branch),
+        _ => show_stack(directory, git_branch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(root: &Path, file: &str, content: &str) {
+        let path = root.join(".commit_message").join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn branch_config(branch: &str, parent: &str) -> String {
+        format!(
+            "branch_name: {}\ncreated_at: now\nparent_branch: {}\n",
+            branch, parent
+        )
+    }
+
+    #[test]
+    fn stack_links_include_branches_with_slashes() {
+        let root = std::env::temp_dir().join(format!("pull_requests_stack_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        write_config(&root, "base.yaml", &branch_config("base", "main"));
+        write_config(
+            &root,
+            "feature/foo.yaml",
+            &branch_config("feature/foo", "base"),
+        );
+        write_config(
+            &root,
+            "feature/deep/bar.yaml",
+            &branch_config("feature/deep/bar", "feature/foo"),
+        );
+        write_config(
+            &root,
+            "no-parent.yaml",
+            "branch_name: no-parent\ncreated_at: now\n",
+        );
+        write_config(&root, "config.yaml", "base_branch: main\n");
+        write_config(
+            &root,
+            "progress/me/report.yaml",
+            &branch_config("report", "main"),
+        );
+
+        let links = stack_links(root.to_str().unwrap());
+        let _ = fs::remove_dir_all(&root);
+        assert_eq!(
+            links,
+            vec![
+                ("base".to_owned(), "main".to_owned()),
+                ("feature/deep/bar".to_owned(), "feature/foo".to_owned()),
+                ("feature/foo".to_owned(), "base".to_owned()),
+            ]
+        );
+        let names: Vec<String> = stack_branches(&links, "main")
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+        assert_eq!(names, vec!["base", "feature/foo", "feature/deep/bar"]);
+    }
+}
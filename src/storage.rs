@@ -10,7 +10,10 @@ use std::{
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::{prompts, ux_utils};
+use crate::{
+    changelog::ChangelogConfig, commit_message::CommitFootersConfig,
+    pr_metadata::PullRequestConfig, progress::ProgressConfig, prompts, ux_utils,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GithubSpace {
@@ -82,6 +85,35 @@ pub struct BranchYamlConfig {
     pub issue_name: Option<String>,
     pub issue_description: Option<String>,
     pub claude_suggestion: Option<String>,
+    pub scopes: Option<Vec<String>>,
+    pub issue_labels: Option<Vec<String>>,
+    // PR body as it was last pushed to Github, the base for merging later edits
+    pub pushed_pr_body: Option<String>,
+    pub pushed_pr_title: Option<String>,
+    // Branch this one is stacked on, the base of its PR
+    pub parent_branch: Option<String>,
+    // Parent commit the branch was last rebased on
+    pub parent_head: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScopeMapping {
+    pub scope: String,
+    pub paths: Vec<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RepoYamlConfig {
+    // Branch PRs are opened against, defaults to the one origin/HEAD points to
+    pub base_branch: Option<String>,
+    pub scopes: Option<Vec<ScopeMapping>>,
+    pub detect_workspace_scopes: Option<bool>,
+    pub scope_labels: Option<bool>,
+    pub commit_footers: Option<CommitFootersConfig>,
+    pub changelog: Option<ChangelogConfig>,
+    pub progress: Option<ProgressConfig>,
+    pub pull_request: Option<PullRequestConfig>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -257,7 +289,7 @@ pub async fn load_branch_config(
         proposed_ouput_message.push_str("\n");
         writeln!(handle, "{}", proposed_ouput_message).unwrap_or_default();
         let _ = handle.flush();
-        let will_commit_pr = prompts::commit_pr_prompt();
+        let will_commit_pr = prompts::commit_pr_prompt(Some(&previous_commit_message));
         if will_commit_pr == true {
             ux_utils::commit_and_push(
                 directory,
@@ -294,12 +326,13 @@ pub fn save_branch_config(
         &git_branch, &directory
     );
     let path = Path::new(directory).join(".commit_message");
-    if !path.exists() {
-        info!("Creating directory: {:?}", &path);
-        fs::create_dir_all(&path).unwrap();
-    }
-
     let file_path = Path::new(&path).join(format!("{}.yaml", &git_branch));
+    // Branches with a slash are saved in subdirectories
+    let branch_path = file_path.parent().unwrap_or(&path);
+    if !branch_path.exists() {
+        info!("Creating directory: {:?}", branch_path);
+        fs::create_dir_all(branch_path).unwrap();
+    }
 
     let local_time: DateTime<Local> = Local::now();
     let local_time_string = local_time.format("%Y-%m-%d %H:%M:%S").to_string();
@@ -356,6 +389,12 @@ pub fn save_branch_config(
             issue_name: None,
             issue_description: None,
             claude_suggestion: None,
+            scopes: None,
+            issue_labels: None,
+            pushed_pr_body: None,
+            pushed_pr_title: None,
+            parent_branch: None,
+            parent_head: None,
         };
         if pr_template.is_some() {
             yaml_config.pr_template = pr_template;
@@ -390,6 +429,93 @@ pub fn save_branch_config(
     Ok(())
 }
 
+// Applies changes to fields that aren't covered by `save_branch_config`
+pub fn update_branch_config<F>(git_branch: &str, directory: &str, update: F) -> Result<(), io::Error>
+where
+    F: FnOnce(&mut BranchYamlConfig),
+{
+    let path = Path::new(directory).join(".commit_message");
+    let file_path = Path::new(&path).join(format!("{}.yaml", &git_branch));
+    // Branches with a slash are saved in subdirectories
+    let branch_path = file_path.parent().unwrap_or(&path);
+    if !branch_path.exists() {
+        info!("Creating directory: {:?}", branch_path);
+        fs::create_dir_all(branch_path)?;
+    }
+
+    let local_time: DateTime<Local> = Local::now();
+    let local_time_string = local_time.format("%Y-%m-%d %H:%M:%S").to_string();
+    let mut branch_config = match get_branch_config(git_branch, directory)? {
+        Some(x) => x,
+        None => BranchYamlConfig {
+            branch_name: git_branch.to_string(),
+            created_at: local_time_string.clone(),
+            updated_at: None,
+            pr_template: None,
+            commit_message: None,
+            additional_message: None,
+            last_commit_exit_code: None,
+            issue_id: None,
+            issue_name: None,
+            issue_description: None,
+            claude_suggestion: None,
+            scopes: None,
+            issue_labels: None,
+            pushed_pr_body: None,
+            pushed_pr_title: None,
+            parent_branch: None,
+            parent_head: None,
+        },
+    };
+    update(&mut branch_config);
+    branch_config.updated_at = Some(local_time_string);
+
+    let file = File::create(&file_path)?;
+    serde_yml::to_writer(file, &branch_config)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(())
+}
+
+pub fn load_repo_config(directory: &str) -> RepoYamlConfig {
+    let file_path = Path::new(directory)
+        .join(".commit_message")
+        .join("config.yaml");
+
+    if !file_path.exists() {
+        info!("No repository config file found at: {:?}", file_path);
+        return RepoYamlConfig::default();
+    }
+
+    match fs::File::open(&file_path) {
+        Ok(file) => {
+            let reader = io::BufReader::new(file);
+            match serde_yml::from_reader::<_, RepoYamlConfig>(reader) {
+                Ok(file_read) => {
+                    info!("Successfully loaded repository config: {:?}", file_read);
+                    file_read
+                }
+                Err(e) => {
+                    let stdout = io::stdout();
+                    let mut handle = io::BufWriter::new(&stdout);
+                    writeln!(
+                        handle,
+                        "\x1b[1;31mFailed to parse {}: {}\x1b[0m",
+                        file_path.display(),
+                        e
+                    )
+                    .unwrap_or_default();
+                    let _ = handle.flush();
+                    process::exit(1);
+                }
+            }
+        }
+        Err(e) => {
+            info!("Failed to open repository config file: {}", e);
+            RepoYamlConfig::default()
+        }
+    }
+}
+
 pub fn load_clickup_config(directory: &str) -> Result<Option<ClickupYamlConfig>, io::Error> {
     let path = Path::new(directory).join(".commit_message");
     let file_path = Path::new(&path).join("clickup.yaml");
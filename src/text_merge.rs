@@ -0,0 +1,270 @@
+// Line based three-way merge, used to combine the PR body edited locally with the
+// one edited on Github, relative to the last version that was pushed
+pub const LOCAL_MARKER: &str = "<<<<<<< local";
+pub const BASE_MARKER: &str = "||||||| last pushed";
+pub const SEPARATOR_MARKER: &str = "=======";
+pub const REMOTE_MARKER: &str = ">>>>>>> github";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeChunk {
+    Resolved(Vec<String>),
+    Conflict {
+        local: Vec<String>,
+        base: Vec<String>,
+        remote: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Hunk {
+    base_start: usize,
+    base_end: usize,
+    lines: Vec<String>,
+    is_local: bool,
+}
+
+// Longest common subsequence of lines, as pairs of indexes
+fn matched_lines(base: &[&str], other: &[&str]) -> Vec<(usize, usize)> {
+    let mut lengths = vec![vec![0usize; other.len() + 1]; base.len() + 1];
+    for i in (0..base.len()).rev() {
+        for j in (0..other.len()).rev() {
+            lengths[i][j] = match base[i] == other[j] {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+    let mut pairs = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < base.len() && j < other.len() {
+        if base[i] == other[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+fn hunks(base: &[&str], other: &[&str], is_local: bool) -> Vec<Hunk> {
+    let mut pairs = matched_lines(base, other);
+    pairs.push((base.len(), other.len()));
+    let mut hunks = vec![];
+    let (mut base_index, mut other_index) = (0, 0);
+    for (base_match, other_match) in pairs {
+        if base_match > base_index || other_match > other_index {
+            hunks.push(Hunk {
+                base_start: base_index,
+                base_end: base_match,
+                lines: other[other_index..other_match]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+                is_local,
+            });
+        }
+        base_index = base_match + 1;
+        other_index = other_match + 1;
+    }
+    hunks
+}
+
+// Applies the hunks of one side to the base lines between `start` and `end`
+fn apply_hunks(base: &[&str], start: usize, end: usize, hunks: &[&Hunk]) -> Vec<String> {
+    let mut lines = vec![];
+    let mut index = start;
+    for hunk in hunks {
+        lines.extend(base[index..hunk.base_start].iter().map(|l| l.to_string()));
+        lines.extend(hunk.lines.iter().cloned());
+        index = hunk.base_end;
+    }
+    lines.extend(base[index..end].iter().map(|l| l.to_string()));
+    lines
+}
+
+pub fn merge(base: &str, local: &str, remote: &str) -> Vec<MergeChunk> {
+    let base_lines: Vec<&str> = base.lines().collect();
+    let local_lines: Vec<&str> = local.lines().collect();
+    let remote_lines: Vec<&str> = remote.lines().collect();
+
+    let mut all_hunks = hunks(&base_lines, &local_lines, true);
+    all_hunks.extend(hunks(&base_lines, &remote_lines, false));
+    all_hunks.sort_by_key(|h| (h.base_start, h.base_end));
+
+    let mut chunks = vec![];
+    let mut base_index = 0;
+    let mut position = 0;
+    while position < all_hunks.len() {
+        // Hunks that overlap or touch end up in the same region
+        let region_start = all_hunks[position].base_start;
+        let mut region_end = all_hunks[position].base_end;
+        let mut region = vec![&all_hunks[position]];
+        position += 1;
+        while position < all_hunks.len() && all_hunks[position].base_start <= region_end {
+            region_end = region_end.max(all_hunks[position].base_end);
+            region.push(&all_hunks[position]);
+            position += 1;
+        }
+
+        if region_start > base_index {
+            chunks.push(MergeChunk::Resolved(
+                base_lines[base_index..region_start]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+            ));
+        }
+        let local_hunks: Vec<&Hunk> = region.iter().copied().filter(|h| h.is_local).collect();
+        let remote_hunks: Vec<&Hunk> = region.iter().copied().filter(|h| !h.is_local).collect();
+        let local = apply_hunks(&base_lines, region_start, region_end, &local_hunks);
+        let remote = apply_hunks(&base_lines, region_start, region_end, &remote_hunks);
+        let chunk = match (local_hunks.is_empty(), remote_hunks.is_empty()) {
+            (_, true) => MergeChunk::Resolved(local),
+            (true, false) => MergeChunk::Resolved(remote),
+            (false, false) if local == remote => MergeChunk::Resolved(local),
+            (false, false) => MergeChunk::Conflict {
+                local,
+                base: base_lines[region_start..region_end]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+                remote,
+            },
+        };
+        chunks.push(chunk);
+        base_index = region_end;
+    }
+    if base_index < base_lines.len() {
+        chunks.push(MergeChunk::Resolved(
+            base_lines[base_index..].iter().map(|l| l.to_string()).collect(),
+        ));
+    }
+    chunks
+}
+
+pub fn has_conflicts(chunks: &[MergeChunk]) -> bool {
+    chunks
+        .iter()
+        .any(|c| matches!(c, MergeChunk::Conflict { .. }))
+}
+
+pub fn render(chunks: &[MergeChunk]) -> String {
+    let mut lines: Vec<String> = vec![];
+    for chunk in chunks {
+        match chunk {
+            MergeChunk::Resolved(resolved) => lines.extend(resolved.iter().cloned()),
+            MergeChunk::Conflict {
+                local,
+                base,
+                remote,
+            } => {
+                lines.push(LOCAL_MARKER.to_owned());
+                lines.extend(local.iter().cloned());
+                lines.push(BASE_MARKER.to_owned());
+                lines.extend(base.iter().cloned());
+                lines.push(SEPARATOR_MARKER.to_owned());
+                lines.extend(remote.iter().cloned());
+                lines.push(REMOTE_MARKER.to_owned());
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+pub fn has_conflict_markers(text: &str) -> bool {
+    text.lines()
+        .any(|l| l == LOCAL_MARKER || l == SEPARATOR_MARKER || l == REMOTE_MARKER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "## Summary\nfirst line\nsecond line\nthird line";
+
+    fn merged(base: &str, local: &str, remote: &str) -> String {
+        let chunks = merge(base, local, remote);
+        assert!(!has_conflicts(&chunks));
+        render(&chunks)
+    }
+
+    #[test]
+    fn only_local_changed() {
+        let local = "## Summary\nfirst line\nsecond line edited locally\nthird line";
+        assert_eq!(merged(BASE, local, BASE), local);
+    }
+
+    #[test]
+    fn only_remote_changed() {
+        let remote = "## Summary\nfirst line\nsecond line\nthird line\nadded on Github";
+        assert_eq!(merged(BASE, BASE, remote), remote);
+    }
+
+    #[test]
+    fn both_changed_different_lines() {
+        let local = "## Summary\nfirst line edited locally\nsecond line\nthird line";
+        let remote = "## Summary\nfirst line\nsecond line\nthird line edited on Github";
+        assert_eq!(
+            merged(BASE, local, remote),
+            "## Summary\nfirst line edited locally\nsecond line\nthird line edited on Github"
+        );
+    }
+
+    #[test]
+    fn both_made_the_same_change() {
+        let edited = "## Summary\nfirst line\nsecond line edited\nthird line";
+        assert_eq!(merged(BASE, edited, edited), edited);
+    }
+
+    #[test]
+    fn both_changed_the_same_line() {
+        let local = "## Summary\nfirst line\nsecond line edited locally\nthird line";
+        let remote = "## Summary\nfirst line\nsecond line edited on Github\nthird line";
+        let chunks = merge(BASE, local, remote);
+        assert!(has_conflicts(&chunks));
+        assert_eq!(
+            chunks,
+            vec![
+                MergeChunk::Resolved(vec!["## Summary".to_owned(), "first line".to_owned()]),
+                MergeChunk::Conflict {
+                    local: vec!["second line edited locally".to_owned()],
+                    base: vec!["second line".to_owned()],
+                    remote: vec!["second line edited on Github".to_owned()],
+                },
+                MergeChunk::Resolved(vec!["third line".to_owned()]),
+            ]
+        );
+        let rendered = render(&chunks);
+        assert!(has_conflict_markers(&rendered));
+        assert_eq!(
+            rendered,
+            format!(
+                "## Summary\nfirst line\n{}\nsecond line edited locally\n{}\nsecond line\n{}\nsecond line edited on Github\n{}\nthird line",
+                LOCAL_MARKER, BASE_MARKER, SEPARATOR_MARKER, REMOTE_MARKER
+            )
+        );
+    }
+
+    #[test]
+    fn empty_base() {
+        // Without a pushed body, two different bodies conflict as a whole
+        let local = "local body";
+        let remote = "remote body";
+        assert_eq!(
+            merge("", local, remote),
+            vec![MergeChunk::Conflict {
+                local: vec![local.to_owned()],
+                base: vec![],
+                remote: vec![remote.to_owned()],
+            }]
+        );
+        assert_eq!(merged("", local, ""), local);
+        assert_eq!(merged("", "", remote), remote);
+        // The update falls back to the remote body as the base, so the local body wins
+        assert_eq!(merged(remote, local, remote), local);
+    }
+}
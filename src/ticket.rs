@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     fs,
     io::{self, Write},
     path::Path,
@@ -12,7 +13,7 @@ use reqwest::Client;
 use serde_json;
 
 use crate::{
-    branch_utils, prompts,
+    branch_utils, prompt_templates, prompts,
     storage::{
         self, BranchYamlConfig, get_branch_config, load_clickup_config, load_github_config,
         save_branch_config,
@@ -136,6 +137,7 @@ async fn automation_from_issue_id(
             git_branch = Some(branch_data.branch_name);
         }
         false => {
+            let mut issue_labels: Vec<String> = vec![];
             let cmd_arg = format!(
                 "cd {directory} && gh issue view {issue_id} --json assignees,author,body,closed,closedAt,closedByPullRequestsReferences,comments,createdAt,id,isPinned,labels,milestone,number,projectCards,reactionGroups,state,stateReason,title,updatedAt,url",
             ).to_owned();
@@ -160,6 +162,15 @@ async fn automation_from_issue_id(
 
                                 // Extract title as name
                                 issue_name = github_issue.title.clone();
+
+                                issue_labels = github_issue
+                                    .labels
+                                    .clone()
+                                    .unwrap_or_default()
+                                    .iter()
+                                    .filter_map(|l| l.get("name").and_then(|n| n.as_str()))
+                                    .map(|n| n.to_owned())
+                                    .collect();
                             }
                             Err(e) => {
                                 debug!("Failed to parse GitHub issue JSON: {}", e);
@@ -200,13 +211,19 @@ async fn automation_from_issue_id(
                 issue_description.clone(),
                 None,
             );
+            let _ = storage::update_branch_config(&built_git_branch, directory, |config| {
+                config.issue_labels = Some(issue_labels);
+            });
         }
     }
 
     if claude_suggestion.is_none() {
-        let prompt_header = "You are a technical product manager.\nGiven the following  GitHub issue text, extend the issue to support the developer implementing it.\n\nAdd whatever could be useful:\n- debug steps;\n- file paths to potentially look into;\n- helpful notes to keep in mind;\n- whatever might be helpful context.\n\n\n\n
-\"";
         let prompt_text = &issue_description.clone().unwrap();
+        let prompt_header = prompt_templates::render_prompt(
+            directory,
+            prompt_templates::ISSUE_EXPANSION_TEMPLATE,
+            &HashMap::from([("issue_title", issue_name.clone().unwrap_or_default())]),
+        );
 
         let claude_suggestion_prompt_result =
             prompt_claude_one_off(&prompt_header, &prompt_text, directory, mcp_config)
@@ -281,7 +298,12 @@ pub async fn ticket(
                 process::exit(1);
             }
             let pr_template = stored_config.unwrap().pr_template.unwrap();
-            let _ = branch_utils::update_pull_request(directory, &pr_template);
+            let _ = branch_utils::update_pull_request(
+                directory,
+                git_branch,
+                &pr_template,
+                matches.is_present("ci_mode"),
+            );
         }
         ("create_pr_template", Some(_arg)) => {
             let issue_id = branch_utils::issue_id(&git_branch);
@@ -315,7 +337,12 @@ pub async fn ticket(
             match update_pr_prompt {
                 Ok(true) => {
                     if let Some(template) = pr_template {
-                        let _ = branch_utils::update_pull_request(directory, &template);
+                        let _ = branch_utils::update_pull_request(
+                            directory,
+                            git_branch,
+                            &template,
+                            matches.is_present("ci_mode"),
+                        );
                     } else {
                         writeln!(handle, "No PR template found to update with").unwrap_or_default();
                         let _ = handle.flush();
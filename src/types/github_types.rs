@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GithubIssue {
@@ -44,6 +45,24 @@ pub struct GithubProjectItem {
     pub title: Option<String>,
     #[serde(rename = "shipped date")]
     pub shipped_date: Option<String>,
+    // Any other project field, keyed by its lowercase name
+    #[serde(flatten)]
+    pub fields: HashMap<String, serde_json::Value>,
+}
+
+impl GithubProjectItem {
+    pub fn field(&self, name: &str) -> Option<String> {
+        match name.to_lowercase().as_str() {
+            "start date" => self.start_date.clone(),
+            "end date" => self.end_date.clone(),
+            "shipped date" => self.shipped_date.clone(),
+            other => self.fields.get(other).and_then(|v| match v {
+                serde_json::Value::String(x) => Some(x.clone()),
+                serde_json::Value::Number(x) => Some(x.to_string()),
+                _ => None,
+            }),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,7 +107,8 @@ pub struct GithubMilestone {
     pub state: Option<String>,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
-    #[serde(rename = "dueOn")]
+    // The REST API uses snake case, the gh CLI camel case
+    #[serde(rename = "dueOn", alias = "due_on")]
     pub due_on: Option<String>,
     pub closed_at: Option<String>,
 }
@@ -99,3 +119,22 @@ pub struct GithubProjectResponse {
     #[serde(rename = "totalCount")]
     pub total_count: Option<i16>,
 }
+
+// `gh pr view --json` output, only the fields that are requested are set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GithubPullRequest {
+    pub number: Option<u64>,
+    pub title: Option<String>,
+    pub body: Option<String>,
+    #[serde(rename = "isDraft")]
+    pub is_draft: Option<bool>,
+    pub labels: Option<Vec<serde_json::Value>>,
+    pub assignees: Option<Vec<serde_json::Value>>,
+    #[serde(rename = "autoMergeRequest")]
+    pub auto_merge_request: Option<serde_json::Value>,
+    pub files: Option<Vec<serde_json::Value>>,
+    pub state: Option<String>,
+    #[serde(rename = "baseRefName")]
+    pub base_ref_name: Option<String>,
+    pub url: Option<String>,
+}
@@ -4,7 +4,7 @@ use serde_json::Value;
 use std::{
     fs,
     io::{self, Write},
-    process::{self, Command, Stdio},
+    process::{self, Command, Output, Stdio},
     time::Duration,
 };
 
@@ -23,6 +23,22 @@ pub fn parse_claude_response(stdout: &str) -> Result<Value, io::Error> {
     Ok(final_json)
 }
 
+// The prompt goes through stdin so diffs and templates never reach the shell
+pub fn run_claude_prompt(directory: &str, prompt_text: &str) -> Result<Output, io::Error> {
+    info!("Claude prompt: {:?}", prompt_text);
+    let mut child = Command::new("claude")
+        .args(["--model", "sonnet", "--output-format", "json", "-p"])
+        .current_dir(directory)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(prompt_text.as_bytes())?;
+    }
+    child.wait_with_output()
+}
+
 pub fn prompt_claude_one_off(
     prompt_header: &str,
     prompt_text: &str,
@@ -49,7 +65,10 @@ pub fn prompt_claude_one_off(
         cmd_arg.push_str(&format!(r#"--mcp-config={}"#, mcp_config.unwrap()));
     }
 
-    cmd_arg.push_str(&format!(r#" -p {}"#, prompt_header));
+    cmd_arg.push_str(&format!(
+        r#" -p '{}'"#,
+        prompt_header.replace("'", "'\\''")
+    ));
 
     let output = Command::new("sh")
         .arg("-c")
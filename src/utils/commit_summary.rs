@@ -0,0 +1,202 @@
+use lazy_static::lazy_static;
+use log::{debug, info};
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::{
+    branch_utils, commit_message::MAX_SUBJECT_LENGTH, scopes, utils::git::git_output,
+};
+
+lazy_static! {
+    static ref HUNK_HEADER_REGEX: Regex = Regex::new(r"^@@ [^@]* @@\s?(.*)$").unwrap();
+    static ref FUNCTION_KEYWORD_REGEX: Regex = Regex::new(
+        r"(?:fn|function|def|class|func|impl|interface|struct|enum)\s+([A-Za-z_$][\w$]*)"
+    )
+    .unwrap();
+    static ref FUNCTION_CALL_REGEX: Regex = Regex::new(r"([A-Za-z_$][\w$]*)\s*[(=:]").unwrap();
+}
+
+#[derive(Debug, Default)]
+pub struct StagedDiffStats {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub renamed: Vec<(String, String)>,
+    pub modified: Vec<String>,
+    pub functions: Vec<String>,
+}
+
+impl StagedDiffStats {
+    pub fn file_count(&self) -> usize {
+        self.added.len() + self.removed.len() + self.renamed.len() + self.modified.len()
+    }
+
+    pub fn all_paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = vec![];
+        paths.extend(self.added.iter().cloned());
+        paths.extend(self.removed.iter().cloned());
+        paths.extend(self.renamed.iter().map(|(_, to)| to.clone()));
+        paths.extend(self.modified.iter().cloned());
+        paths
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CommitSuggestion {
+    pub commit_type: Option<String>,
+    pub scope: Option<String>,
+    pub subject: String,
+    // Built from the staged files, offered next to the subject from the branch name
+    pub stats_subject: Option<String>,
+    pub summary: String,
+}
+
+fn function_from_hunk_context(context: &str) -> Option<String> {
+    if let Some(caps) = FUNCTION_KEYWORD_REGEX.captures(context) {
+        return Some(caps.get(1).unwrap().as_str().to_owned());
+    }
+    FUNCTION_CALL_REGEX
+        .captures(context)
+        .map(|caps| caps.get(1).unwrap().as_str().to_owned())
+}
+
+pub fn staged_diff_stats(directory: &str) -> StagedDiffStats {
+    let mut stats = StagedDiffStats::default();
+
+    let name_status = git_output(directory, "diff --cached --name-status -M").unwrap_or_default();
+    for line in name_status.lines() {
+        let parts: Vec<&str> = line.split('\t').collect();
+        if parts.len() < 2 {
+            continue;
+        }
+        match parts[0].chars().next() {
+            Some('A') => stats.added.push(parts[1].to_owned()),
+            Some('D') => stats.removed.push(parts[1].to_owned()),
+            Some('R') if parts.len() > 2 => stats
+                .renamed
+                .push((parts[1].to_owned(), parts[2].to_owned())),
+            _ => stats.modified.push(parts[parts.len() - 1].to_owned()),
+        }
+    }
+
+    let hunks = git_output(directory, "--no-pager diff --cached -U0").unwrap_or_default();
+    for line in hunks.lines() {
+        let Some(caps) = HUNK_HEADER_REGEX.captures(line) else {
+            continue;
+        };
+        let context = caps.get(1).map(|c| c.as_str()).unwrap_or("");
+        if let Some(function) = function_from_hunk_context(context) {
+            if !stats.functions.contains(&function) {
+                stats.functions.push(function);
+            }
+        }
+    }
+    debug!("Staged diff stats: {:?}", stats);
+    stats
+}
+
+fn file_stem(path: &str) -> String {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    file_name.split('.').next().unwrap_or(file_name).to_owned()
+}
+
+fn list_names(names: &[String]) -> String {
+    match names.len() {
+        0 => "".to_owned(),
+        1 => names[0].clone(),
+        2 => format!("{} and {}", names[0], names[1]),
+        _ => format!("{}, {} and {} more", names[0], names[1], names.len() - 2),
+    }
+}
+
+// The first directory shared by every changed path, used as a fallback scope
+fn common_directory(paths: &[String]) -> Option<String> {
+    let mut directories: HashMap<&str, usize> = HashMap::new();
+    for path in paths {
+        let parts: Vec<&str> = path.split('/').collect();
+        if parts.len() < 2 {
+            return None;
+        }
+        *directories.entry(parts[0]).or_insert(0) += 1;
+    }
+    if directories.len() == 1 {
+        return directories.keys().next().map(|d| d.to_string());
+    }
+    None
+}
+
+fn subject_from_stats(stats: &StagedDiffStats) -> Option<String> {
+    let total = stats.file_count();
+    if total == 0 {
+        return None;
+    }
+    if stats.renamed.len() == total && total == 1 {
+        let (from, to) = &stats.renamed[0];
+        return Some(format!("rename {} to {}", file_stem(from), file_stem(to)));
+    }
+    if stats.added.len() == total {
+        let names: Vec<String> = stats.added.iter().map(|p| file_stem(p)).collect();
+        return Some(format!("add {}", list_names(&names)));
+    }
+    if stats.removed.len() == total {
+        let names: Vec<String> = stats.removed.iter().map(|p| file_stem(p)).collect();
+        return Some(format!("remove {}", list_names(&names)));
+    }
+    if !stats.functions.is_empty() && stats.functions.len() <= 3 {
+        return Some(format!("update {}", list_names(&stats.functions)));
+    }
+    let names: Vec<String> = stats.all_paths().iter().map(|p| file_stem(p)).collect();
+    Some(format!("update {}", list_names(&names)))
+}
+
+fn type_from_stats(stats: &StagedDiffStats, proposed_type: Option<String>) -> Option<String> {
+    let total = stats.file_count();
+    if total > 0 && stats.renamed.len() == total {
+        return Some("refactor".to_owned());
+    }
+    proposed_type
+}
+
+fn describe_stats(stats: &StagedDiffStats) -> String {
+    let mut description = format!(
+        "{} added, {} removed, {} renamed, {} modified",
+        stats.added.len(),
+        stats.removed.len(),
+        stats.renamed.len(),
+        stats.modified.len()
+    );
+    if !stats.functions.is_empty() {
+        description.push_str(&format!("; touched {}", list_names(&stats.functions)));
+    }
+    description
+}
+
+pub fn suggest_commit(
+    directory: &str,
+    git_branch: &str,
+    proposed_type: Option<String>,
+) -> Option<CommitSuggestion> {
+    let stats = staged_diff_stats(directory);
+    let branch_subject = branch_utils::branch_name(git_branch).to_lowercase();
+    let stats_subject = subject_from_stats(&stats)
+        .map(|subject| subject.chars().take(MAX_SUBJECT_LENGTH).collect::<String>());
+    let subject = match branch_subject.is_empty() {
+        false => Some(branch_subject),
+        true => stats_subject.clone(),
+    }?;
+    let commit_type = type_from_stats(&stats, proposed_type);
+    let changed_paths = stats.all_paths();
+    let scope = scopes::infer_scopes(&scopes::configured_scopes(directory), &changed_paths)
+        .first()
+        .cloned()
+        .or_else(|| common_directory(&changed_paths));
+
+    let suggestion = CommitSuggestion {
+        commit_type,
+        scope,
+        subject: subject.chars().take(MAX_SUBJECT_LENGTH).collect(),
+        stats_subject,
+        summary: describe_stats(&stats),
+    };
+    info!("Offline commit suggestion: {:?}", suggestion);
+    Some(suggestion)
+}
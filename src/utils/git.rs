@@ -0,0 +1,54 @@
+use log::{debug, info};
+use std::process::{Command, Stdio};
+
+use crate::pr_metadata::shell_quote;
+
+// `args` is passed to the shell as is, values coming from users have to be quoted by the caller
+fn git_command(directory: &str, args: &str) -> Command {
+    let cmd_arg = format!("cd {} && git {}", shell_quote(directory), args);
+    debug!("Executing command: {}", cmd_arg);
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(cmd_arg);
+    command
+}
+
+// Trimmed stdout, or stderr when the command failed
+pub fn git_result(directory: &str, args: &str) -> Result<String, String> {
+    match git_command(directory, args).output() {
+        Ok(output) if output.status.success() => {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+        }
+        Ok(output) => {
+            let error = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+            info!("git {} failed: {}", args, error);
+            Err(error)
+        }
+        Err(e) => {
+            info!("git {} failed: {}", args, e);
+            Err(e.to_string())
+        }
+    }
+}
+
+pub fn git_output(directory: &str, args: &str) -> Option<String> {
+    git_result(directory, args).ok()
+}
+
+pub fn run_git(directory: &str, args: &str) -> bool {
+    git_result(directory, args).is_ok()
+}
+
+// For commands the user has to follow, like rebases and pushes
+pub fn run_git_inherit(directory: &str, args: &str) -> bool {
+    match git_command(directory, args)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+    {
+        Ok(status) => status.success(),
+        Err(e) => {
+            info!("git {} failed: {}", args, e);
+            false
+        }
+    }
+}
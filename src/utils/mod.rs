@@ -1,3 +1,5 @@
 pub mod claude;
+pub mod commit_summary;
 pub mod extract_clickup_spaces_data;
 pub mod extract_github_spaces_data;
+pub mod git;
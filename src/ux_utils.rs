@@ -7,6 +7,26 @@ use log::{debug, info};
 
 use crate::{branch_utils, prompts};
 
+// Prints the message in red and stops, for errors the command can't recover from
+pub fn exit_with_error(message: &str) -> ! {
+    let stdout = io::stdout();
+    let mut handle = io::BufWriter::new(&stdout);
+    writeln!(handle, "\x1b[1;31m{}\x1b[0m", message).unwrap_or_default();
+    let _ = handle.flush();
+    process::exit(1);
+}
+
+// What pushing and creating the PR needs once the commits are done
+pub struct PushContext<'a> {
+    pub directory: &'a str,
+    pub git_branch: &'a str,
+    pub pr_template: Option<String>,
+    pub no_verify: bool,
+    pub ci_mode: bool,
+    pub github_api_token: Option<&'a str>,
+    pub has_gh: bool,
+}
+
 pub async fn commit_and_push(
     directory: &str,
     commit_message: String,
@@ -46,13 +66,28 @@ pub async fn commit_and_push(
         let _ = handle.flush();
         process::exit(1);
     }
-    if pr_template.is_some() {
+    let context = PushContext {
+        directory,
+        git_branch,
+        pr_template,
+        no_verify,
+        ci_mode,
+        github_api_token,
+        has_gh,
+    };
+    push_after_commit(context, commit_message).await;
+}
+
+pub async fn push_after_commit(context: PushContext<'_>, commit_message: String) {
+    let stdout = io::stdout(); // get the global stdout entity
+    let mut handle = io::BufWriter::new(&stdout); // optional: wrap that handle in a buffer
+    if context.pr_template.is_some() {
         let pr_template_message =
             "\x1b[1;32mThere is a PR template available.\x1b[1;0m Use `commit --show-pr-template` to display it.".to_owned();
         writeln!(handle, "{}", pr_template_message).unwrap_or_default();
         let _ = handle.flush();
     }
-    let will_push_pr = match ci_mode {
+    let will_push_pr = match context.ci_mode {
         true => true,
         false => prompts::push_pr_prompt(),
     };
@@ -60,14 +95,14 @@ pub async fn commit_and_push(
     if will_push_pr == true {
         info!("Will push pr? {}", will_push_pr);
         let _ = branch_utils::push_pr(
-            directory,
-            no_verify,
-            ci_mode,
-            github_api_token,
-            git_branch,
+            context.directory,
+            context.no_verify,
+            context.ci_mode,
+            context.github_api_token,
+            context.git_branch,
             Some(&commit_message),
-            pr_template,
-            has_gh,
+            context.pr_template,
+            context.has_gh,
         )
         .await
         .unwrap();